@@ -1,22 +1,56 @@
+pub mod catalogue;
 mod episode;
+mod episode_ranges;
 mod episodes;
+mod offered_episode;
+mod season_filter;
+mod seen_episode;
+mod selection_strategy;
+mod settings;
+pub mod storage;
 
 pub use super::error::Error;
-pub use episode::Episode;
-use episodes::EPISODES;
-use rand::seq::IndexedRandom;
-use std::fs;
+pub use catalogue::{Catalogue, Show, ShowID};
+use chrono::{DateTime, Utc};
+pub use episode::{Episode, EpisodeMetadata};
+pub use episode_ranges::EpisodeRanges;
+pub use offered_episode::OfferedEpisode;
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+pub use season_filter::SeasonFilter;
+pub use seen_episode::{SeenEpisode, SeenEpisodeSource};
+pub use selection_strategy::{SelectionStrategy, SelectionStrategyKind};
+pub use settings::{Language, UserSettings};
 use std::{
+    collections::{HashMap, HashSet},
     fmt::Display,
-    fs::File,
-    io::{Read, Write},
-    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
 };
 
-pub fn new(storage_path: PathBuf) -> Application {
-    Application { storage_path }
+type SeenEpisodesStore = dyn storage::SeenEpisodesStore + Send + Sync;
+
+/// Сколько последних предложенных серий не предлагать снова, пока есть из чего выбрать.
+const RECENT_OFFERS_LIMIT: usize = 5;
+
+pub fn new(store: Arc<SeenEpisodesStore>, catalogue: Catalogue) -> Application {
+    new_with_rng(store, catalogue, StdRng::from_rng(&mut rand::rng()))
+}
+
+/// Приложение с заданным генератором случайных чисел, чтобы последовательность
+/// выбранных серий можно было повторить.
+pub fn new_with_rng(
+    store: Arc<SeenEpisodesStore>,
+    catalogue: Catalogue,
+    rng: StdRng,
+) -> Application {
+    Application {
+        store,
+        catalogue,
+        user_locks: Mutex::new(HashMap::new()),
+        rng: Mutex::new(rng),
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct UserID(u64);
 
 impl UserID {
@@ -32,301 +66,1153 @@ impl Display for UserID {
 }
 
 pub struct Application {
-    storage_path: PathBuf,
+    store: Arc<SeenEpisodesStore>,
+    catalogue: Catalogue,
+    /// Диспетчер бота обрабатывает апдейты параллельно, поэтому изменения истории
+    /// одного пользователя выполняются строго по очереди под его личным мьютексом.
+    user_locks: Mutex<HashMap<UserID, Arc<Mutex<()>>>>,
+    /// Источник зёрен для выбора серий. Каждый выбор идёт от своего зерна, которое
+    /// пишется в лог, поэтому любой выбор из жалобы пользователя можно воспроизвести.
+    rng: Mutex<StdRng>,
 }
 
 impl Application {
-    pub fn get_next_episode(&self, user_id: UserID) -> Result<Episode, Error> {
-        let user_storage_path = self.build_user_storage_path(&user_id);
-        let seen_episodes = self.read_db_from_file(&user_storage_path)?;
-        let selected_episode = self.select_next_episode(&seen_episodes)?;
+    /// Сериалы из каталога в том порядке, в котором они там записаны.
+    pub fn shows(&self) -> &[Show] {
+        self.catalogue.shows()
+    }
+
+    /// Сериал, который выбрал пользователь. Если он ничего не выбирал или выбранного
+    /// сериала больше нет в каталоге, используется сериал по умолчанию.
+    pub fn active_show(&self, user_id: UserID) -> Result<&Show, Error> {
+        let settings = self.store.load_settings(&user_id)?;
+
+        let show = settings
+            .show
+            .and_then(|show_id| self.catalogue.show(&show_id).ok())
+            .unwrap_or_else(|| self.catalogue.default_show());
 
-        Ok(selected_episode)
+        Ok(show)
     }
 
-    fn build_user_storage_path(&self, user_id: &UserID) -> PathBuf {
-        self.storage_path.join(format!("{user_id}.txt"))
+    pub fn show(&self, show_id: &ShowID) -> Result<&Show, Error> {
+        self.catalogue.show(show_id)
     }
 
-    fn read_db_from_file(&self, path: &Path) -> Result<Vec<Episode>, std::io::Error> {
-        let mut file = match File::open(path) {
-            Ok(file) => file,
-            Err(err) => match err.kind() {
-                std::io::ErrorKind::NotFound => return Ok(Vec::new()),
-                _ => return Err(err),
-            },
-        };
+    pub fn select_show(&self, user_id: UserID, show_id: &ShowID) -> Result<&Show, Error> {
+        let show = self.catalogue.show(show_id)?;
 
-        let mut seen_episodes = String::new();
-        file.read_to_string(&mut seen_episodes)?;
+        self.update_settings(user_id, |settings| {
+            settings.show = Some(show.id().clone());
+        })?;
 
-        Ok(seen_episodes
-            .trim()
-            .lines()
-            .rev()
-            .map(Episode::from)
-            .collect())
+        Ok(show)
     }
 
-    fn select_next_episode(&self, seen_episodes: &[Episode]) -> Result<Episode, Error> {
-        let seen_set: std::collections::HashSet<&Episode> = seen_episodes.iter().collect();
+    pub fn settings(&self, user_id: UserID) -> Result<UserSettings, Error> {
+        self.store.load_settings(&user_id)
+    }
 
-        let next_episode = EPISODES
-            .choose_multiple(&mut rand::rng(), EPISODES.len())
-            .map(|&s| Episode::from(s))
-            .find(|ep| !seen_set.contains(ep));
+    /// Изменить настройки пользователя и сохранить их. Возвращает новые настройки.
+    pub fn update_settings(
+        &self,
+        user_id: UserID,
+        update: impl FnOnce(&mut UserSettings),
+    ) -> Result<UserSettings, Error> {
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
 
-        match next_episode {
-            Some(episode) => Ok(episode),
-            None => Err(Error::NoUnseenEpisodes),
-        }
+        let mut settings = self.store.load_settings(&user_id)?;
+        update(&mut settings);
+        self.store.save_settings(&user_id, &settings)?;
+
+        Ok(settings)
     }
 
-    pub fn mark_seen(&self, user_id: UserID, episode: Episode) -> Result<(), Error> {
-        let user_storage_path = self.build_user_storage_path(&user_id);
-        let mut seen_episodes = self.read_db_from_file(&user_storage_path)?;
+    /// Сезоны сериала, которыми пользователь ограничил выбор следующей серии.
+    pub fn season_filter(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+    ) -> Result<Option<SeasonFilter>, Error> {
+        let settings = self.store.load_settings(&user_id)?;
 
-        seen_episodes.push(episode);
+        Ok(settings.season_filters.get(show_id).copied())
+    }
+
+    /// Ограничить выбор следующей серии сезонами из фильтра или, если фильтра нет,
+    /// снять ограничение. Фильтр без единой серии сериала не сохраняется.
+    pub fn set_season_filter(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+        season_filter: Option<SeasonFilter>,
+    ) -> Result<(), Error> {
+        let show = self.catalogue.show(show_id)?;
+        if let Some(season_filter) = season_filter {
+            let has_episodes = show
+                .episodes()
+                .iter()
+                .any(|episode| season_filter.contains(episode.season()));
+            if !has_episodes {
+                return Err(Error::SeasonFilterError(format!(
+                    "в сериале нет серий из этих сезонов: show={show_id}, seasons={season_filter}"
+                )));
+            }
+        }
 
-        self.save_db_to_file(seen_episodes, &user_storage_path)?;
+        self.update_settings(user_id, |settings| {
+            match season_filter {
+                Some(season_filter) => settings
+                    .season_filters
+                    .insert(show_id.clone(), season_filter),
+                None => settings.season_filters.remove(show_id),
+            };
+        })?;
 
         Ok(())
     }
 
-    fn save_db_to_file(
-        &self,
-        seen_episodes: Vec<Episode>,
-        path: &Path,
-    ) -> Result<(), std::io::Error> {
-        if seen_episodes.is_empty() {
-            return Ok(());
+    /// Выбрать следующую серию сериала и запомнить, что она предложена. Недавно
+    /// предложенные серии выбираются, только если больше выбрать нечего.
+    ///
+    /// Если пользователь включил бесконечный просмотр и посмотрел все серии, завершённый
+    /// круг уходит в архив и начинается новый.
+    pub fn get_next_episode(&self, user_id: UserID, show_id: &ShowID) -> Result<Episode, Error> {
+        let show = self.catalogue.show(show_id)?;
+
+        // история читается, проверяется и переносится в архив под одной блокировкой,
+        // иначе отметка, поставленная в это время, уехала бы в завершённый круг
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
+
+        let settings = self.store.load_settings(&user_id)?;
+        let season_filter = settings.season_filters.get(show_id);
+        let seen_episodes = self.store.load(&user_id, show_id)?;
+        let recent_offers =
+            self.store
+                .load_recent_offers(&user_id, show_id, RECENT_OFFERS_LIMIT)?;
+        let recently_offered: HashSet<&Episode> =
+            recent_offers.iter().map(OfferedEpisode::episode).collect();
+
+        let strategy = settings.selection_strategy.strategy();
+        // время тоже входит в выбор (например, у взвешенной стратегии), поэтому
+        // пишется в лог вместе с зерном
+        let now = Utc::now();
+        let seed = self.next_seed();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let result = match Self::select_next_episode(
+            show,
+            &seen_episodes,
+            season_filter,
+            strategy,
+            &recently_offered,
+            now,
+            &mut rng,
+        ) {
+            Err(Error::NoUnseenEpisodes)
+                if settings.auto_reset && is_everything_seen(show, &seen_episodes) =>
+            {
+                match self
+                    .store
+                    .archive_completed(&user_id, show_id, show.episodes())?
+                {
+                    Some(completed_cycles) => {
+                        tracing::info!(
+                            user_id = user_id.0,
+                            show_id = show_id.as_str(),
+                            completed_cycles = completed_cycles,
+                            "все серии просмотрены, начинаем новый круг"
+                        );
+
+                        Self::select_next_episode(
+                            show,
+                            &[],
+                            season_filter,
+                            strategy,
+                            &recently_offered,
+                            now,
+                            &mut rng,
+                        )
+                    }
+                    None => Err(Error::NoUnseenEpisodes),
+                }
+            }
+            result => result,
+        };
+        tracing::info!(
+            user_id = user_id.0,
+            show_id = show_id.as_str(),
+            seed = seed,
+            now = now.to_rfc3339(),
+            strategy = ?settings.selection_strategy,
+            seen_episodes = seen_episodes.len(),
+            recent_offers = recent_offers.len(),
+            episode = result.as_ref().ok().map(Episode::code),
+            "выбрана следующая серия"
+        );
+
+        let episode = result?;
+        self.store
+            .append_offer(&user_id, show_id, OfferedEpisode::new(episode.clone(), now))?;
+
+        Ok(episode)
+    }
+
+    fn select_next_episode(
+        show: &Show,
+        seen_episodes: &[SeenEpisode],
+        season_filter: Option<&SeasonFilter>,
+        strategy: &dyn SelectionStrategy,
+        recently_offered: &HashSet<&Episode>,
+        now: DateTime<Utc>,
+        rng: &mut dyn RngCore,
+    ) -> Result<Episode, Error> {
+        let candidates: Vec<&Episode> = show
+            .episodes()
+            .iter()
+            .filter(|ep| season_filter.is_none_or(|filter| filter.contains(ep.season())))
+            .collect();
+        let fresh_candidates: Vec<&Episode> = candidates
+            .iter()
+            .copied()
+            .filter(|ep| !recently_offered.contains(ep))
+            .collect();
+
+        let next_episode = strategy
+            .select(&fresh_candidates, seen_episodes, now, rng)
+            .or_else(|| strategy.select(&candidates, seen_episodes, now, rng));
+
+        match next_episode {
+            Some(episode) => Ok(episode.clone()),
+            None => Err(Error::NoUnseenEpisodes),
         }
+    }
+
+    /// Найти серию сериала по коду, например, пришедшему из колбека.
+    pub fn find_episode(&self, show_id: &ShowID, code: &str) -> Result<Episode, Error> {
+        self.catalogue.show(show_id)?.find(code).cloned()
+    }
 
-        if let Some(parent) = path.parent() {
-            self.create_directory_if_not_exists(parent)?;
+    pub fn mark_seen(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+        episode: Episode,
+    ) -> Result<(), Error> {
+        if !self.catalogue.show(show_id)?.contains(&episode) {
+            return Err(Error::UnknownEpisode(episode.code().to_string()));
         }
 
-        let mut file = File::create(path)?;
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
 
-        write!(
-            file,
-            "{}",
-            seen_episodes
+        self.store.append(
+            &user_id,
+            show_id,
+            SeenEpisode::new(episode, Some(Utc::now()), SeenEpisodeSource::Bot),
+        )
+    }
+
+    /// Отметить просмотренными серии из набора, например, посмотренные до знакомства
+    /// с ботом. Серии, уже отмеченные в текущем круге, пропускаются, остальные
+    /// сохраняются одной записью. Возвращает отмеченные серии в порядке каталога.
+    pub fn mark_seen_episodes(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+        episode_ranges: &EpisodeRanges,
+    ) -> Result<Vec<Episode>, Error> {
+        let episodes = episode_ranges.resolve(self.catalogue.show(show_id)?)?;
+
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
+
+        let seen_episodes = self.store.load(&user_id, show_id)?;
+        let seen_set: HashSet<&Episode> = seen_episodes.iter().map(SeenEpisode::episode).collect();
+        let unseen_episodes: Vec<Episode> = episodes
+            .into_iter()
+            .filter(|episode| !seen_set.contains(episode))
+            .cloned()
+            .collect();
+
+        let now = Utc::now();
+        self.store.append_all(
+            &user_id,
+            show_id,
+            unseen_episodes
                 .iter()
-                .fold(String::new(), |acc, ep| format!("{}\n{}", ep.code(), acc))
+                .map(|episode| {
+                    SeenEpisode::new(episode.clone(), Some(now), SeenEpisodeSource::Manual)
+                })
+                .collect(),
         )?;
 
-        Ok(())
+        Ok(unseen_episodes)
     }
 
-    fn create_directory_if_not_exists(&self, path: &Path) -> Result<(), std::io::Error> {
-        fs::create_dir_all(path)?;
+    /// Снять одной записью отметки о просмотре с серий из набора. Возвращает серии,
+    /// с которых сняты отметки, в порядке каталога.
+    pub fn unmark_seen_episodes(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+        episode_ranges: &EpisodeRanges,
+    ) -> Result<Vec<Episode>, Error> {
+        let episodes = episode_ranges.resolve(self.catalogue.show(show_id)?)?;
+
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
+
+        let seen_episodes = self.store.load(&user_id, show_id)?;
+        let seen_set: HashSet<&Episode> = seen_episodes.iter().map(SeenEpisode::episode).collect();
+        let marked_episodes: Vec<Episode> = episodes
+            .into_iter()
+            .filter(|episode| seen_set.contains(episode))
+            .cloned()
+            .collect();
+
+        self.store.remove_all(&user_id, show_id, &marked_episodes)?;
+
+        Ok(marked_episodes)
+    }
 
-        Ok(())
+    /// Отменить последнюю отметку о просмотре серии, например, поставленную по ошибке.
+    /// Возвращает `false`, если в текущем круге серия не отмечена.
+    pub fn unmark_seen(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+        episode: &Episode,
+    ) -> Result<bool, Error> {
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
+
+        let removed = self.store.remove_last(&user_id, show_id, Some(episode))?;
+
+        Ok(removed.is_some())
     }
 
-    pub fn list_seen_episodes(&self, user_id: UserID) -> Result<Vec<Episode>, Error> {
-        let user_storage_path = self.build_user_storage_path(&user_id);
-        let seen_episodes = self.read_db_from_file(&user_storage_path)?;
+    /// Отменить самую последнюю отметку о просмотре в текущем круге. Возвращает серию,
+    /// с которой снята отметка, или `None`, если отменять нечего.
+    pub fn undo_last_mark(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+    ) -> Result<Option<Episode>, Error> {
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
+
+        let removed = self.store.remove_last(&user_id, show_id, None)?;
+
+        Ok(removed.map(|seen_episode| seen_episode.episode().clone()))
+    }
+
+    pub fn list_seen_episodes(
+        &self,
+        user_id: UserID,
+        show_id: &ShowID,
+    ) -> Result<Vec<SeenEpisode>, Error> {
+        let seen_episodes = self.store.load(&user_id, show_id)?;
 
         Ok(seen_episodes)
     }
 
-    pub fn clear_seen_episodes(&self, user_id: UserID) -> Result<(), Error> {
-        let user_storage_path = self.build_user_storage_path(&user_id);
+    pub fn clear_seen_episodes(&self, user_id: UserID, show_id: &ShowID) -> Result<(), Error> {
+        let user_lock = self.user_lock(user_id);
+        let _guard = user_lock.lock();
 
-        fs::remove_file(user_storage_path).or_else(|err| match err.kind() {
-            std::io::ErrorKind::NotFound => Ok(()),
-            _ => Err(Error::FileError(err)),
-        })
+        self.store.clear(&user_id, show_id)
+    }
+
+    /// Сколько раз пользователь посмотрел все серии сериала в бесконечном режиме.
+    pub fn completed_cycles(&self, user_id: UserID, show_id: &ShowID) -> Result<u32, Error> {
+        self.store.completed_cycles(&user_id, show_id)
+    }
+
+    fn next_seed(&self) -> u64 {
+        self.rng
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .random()
+    }
+
+    fn user_lock(&self, user_id: UserID) -> UserLock<'_> {
+        let mutex = self
+            .user_locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(user_id)
+            .or_default()
+            .clone();
+
+        UserLock {
+            application: self,
+            user_id,
+            mutex,
+        }
     }
 }
 
+/// Личный мьютекс пользователя. Когда его больше никто не держит и не ждёт, запись
+/// удаляется из `user_locks`, чтобы карта не росла с каждым новым пользователем.
+struct UserLock<'a> {
+    application: &'a Application,
+    user_id: UserID,
+    mutex: Arc<Mutex<()>>,
+}
+
+impl UserLock<'_> {
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.mutex.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for UserLock<'_> {
+    fn drop(&mut self) {
+        let mut user_locks = self
+            .application
+            .user_locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // ссылки раздаются только под блокировкой карты, поэтому если остались лишь
+        // ссылка в карте и наша, новых ожидающих уже не появится
+        if Arc::strong_count(&self.mutex) == 2 {
+            user_locks.remove(&self.user_id);
+        }
+    }
+}
+
+fn is_everything_seen(show: &Show, seen_episodes: &[SeenEpisode]) -> bool {
+    let seen_set: HashSet<&Episode> = seen_episodes.iter().map(SeenEpisode::episode).collect();
+
+    show.episodes()
+        .iter()
+        .all(|episode| seen_set.contains(episode))
+}
+
 #[cfg(test)]
 mod test {
-    use tempfile::{NamedTempFile, TempDir};
+    use std::thread;
+
+    use tempfile::TempDir;
 
     use super::*;
+    use episodes::EPISODES;
 
     fn build_application() -> Application {
-        Application {
-            storage_path: "seen_episodes".into(),
-        }
+        build_application_with_seed(317)
+    }
+
+    fn build_application_with_seed(seed: u64) -> Application {
+        new_with_rng(
+            Arc::new(storage::memory::new()),
+            Catalogue::builtin(),
+            StdRng::seed_from_u64(seed),
+        )
+    }
+
+    fn friends() -> ShowID {
+        ShowID::default()
+    }
+
+    fn build_application_with_two_shows() -> Application {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let catalogue_path = temp_dir.path().join("catalogue.json");
+        std::fs::write(
+            &catalogue_path,
+            r#"{"shows": [
+                {"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01"}]},
+                {"id": "the-office", "name": "Офис", "episodes": [{"code": "s02e01"}]}
+            ]}"#,
+        )
+        .unwrap();
+        let catalogue = catalogue::load(Some(&catalogue_path)).unwrap();
+
+        new(Arc::new(storage::memory::new()), catalogue)
+    }
+
+    #[test]
+    fn application_select_next_episode_fn_returns_any_episode_at_all() {
+        let a = build_application();
+
+        let result = Application::select_next_episode(
+            a.catalogue.default_show(),
+            &Vec::new(),
+            None,
+            SelectionStrategyKind::default().strategy(),
+            &HashSet::new(),
+            Utc::now(),
+            &mut StdRng::seed_from_u64(317),
+        );
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert!(EPISODES.contains(&result.unwrap().code()))
+    }
+
+    #[test]
+    fn application_select_next_episode_fn_depends_only_on_seed() {
+        let a = build_application();
+        let select = |seed| {
+            Application::select_next_episode(
+                a.catalogue.default_show(),
+                &[],
+                None,
+                SelectionStrategyKind::default().strategy(),
+                &HashSet::new(),
+                Utc::now(),
+                &mut StdRng::seed_from_u64(seed),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(select(317), select(317));
+        let picks: HashSet<Episode> = (0..20).map(select).collect();
+        assert!(picks.len() > 1, "picks: {picks:?}");
     }
 
     #[test]
-    fn application_build_user_storage_path_fn_works_as_expected() {
+    fn application_select_next_episode_fn_repeats_weighted_choice_for_same_seed_and_time() {
         let a = build_application();
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let seen_episodes: Vec<SeenEpisode> = EPISODES[..3]
+            .iter()
+            .map(|&s| {
+                SeenEpisode::new(
+                    Episode::try_from(s).unwrap(),
+                    Some(now - chrono::TimeDelta::days(30)),
+                    SeenEpisodeSource::Bot,
+                )
+            })
+            .collect();
+        let select = || {
+            Application::select_next_episode(
+                a.catalogue.default_show(),
+                &seen_episodes,
+                None,
+                SelectionStrategyKind::Weighted.strategy(),
+                &HashSet::new(),
+                now,
+                &mut StdRng::seed_from_u64(317),
+            )
+            .unwrap()
+        };
 
-        let user_id = UserID(317);
+        assert_eq!(select(), select());
+    }
 
-        let result = a.build_user_storage_path(&user_id);
+    #[test]
+    fn application_get_next_episode_fn_repeats_sequence_for_same_rng_seed() {
+        let user_id = UserID::new(317);
+        let suggest = |a: &Application| -> Vec<Episode> {
+            (0..10)
+                .map(|_| a.get_next_episode(user_id, &friends()).unwrap())
+                .collect()
+        };
 
-        assert_eq!(result, PathBuf::from("seen_episodes/317.txt"));
+        let first = suggest(&build_application_with_seed(42));
+        let second = suggest(&build_application_with_seed(42));
+
+        assert_eq!(first, second);
+        assert_ne!(first, suggest(&build_application_with_seed(43)));
     }
 
     #[test]
-    fn application_read_db_from_file_fn_works_with_non_existing_file() {
+    fn application_select_next_episode_fn_returns_error_if_there_is_no_unseen_episodes() {
         let a = build_application();
 
-        let result = a.read_db_from_file(Path::new("non_existing_file.txt"));
+        let all_episodes: Vec<SeenEpisode> = EPISODES
+            .iter()
+            .map(|&s| {
+                SeenEpisode::new(
+                    Episode::try_from(s).unwrap(),
+                    None,
+                    SeenEpisodeSource::Legacy,
+                )
+            })
+            .collect();
+
+        let result = Application::select_next_episode(
+            a.catalogue.default_show(),
+            &all_episodes,
+            None,
+            SelectionStrategyKind::default().strategy(),
+            &HashSet::new(),
+            Utc::now(),
+            &mut StdRng::seed_from_u64(317),
+        );
 
-        assert!(!result.is_err(), "result is error: {result:#?}");
-        assert_eq!(result.unwrap(), Vec::<Episode>::new());
+        assert!(matches!(result, Err(Error::NoUnseenEpisodes)));
     }
 
     #[test]
-    fn application_read_db_from_file_fn_handles_empty_file() {
+    fn application_get_next_episode_fn_skips_seen_episodes() {
         let a = build_application();
-        let tmpfile = NamedTempFile::new().unwrap();
+        let user_id = UserID::new(317);
 
-        let result = a.read_db_from_file(tmpfile.path());
-        assert!(!result.is_err(), "result is error: {result:#?}");
-        assert_eq!(result.unwrap(), Vec::<Episode>::new());
+        for &code in EPISODES.iter().skip(1) {
+            a.mark_seen(user_id, &friends(), Episode::try_from(code).unwrap())
+                .unwrap();
+        }
+
+        let result = a.get_next_episode(user_id, &friends());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Episode::try_from(EPISODES[0]).unwrap());
     }
 
     #[test]
-    fn application_read_db_from_file_fn_reads_data_from_file() {
+    fn application_list_seen_episodes_fn_returns_marked_episodes_in_viewing_order() {
         let a = build_application();
-        let mut tmpfile = NamedTempFile::new().unwrap();
-        writeln!(tmpfile, "s01e02\ns01e01\n").unwrap();
+        let user_id = UserID::new(317);
+
+        a.mark_seen(user_id, &friends(), Episode::try_from("s03e05").unwrap())
+            .unwrap();
+        a.mark_seen(user_id, &friends(), Episode::try_from("s01e01").unwrap())
+            .unwrap();
 
-        let result = a.read_db_from_file(tmpfile.path());
-        assert!(!result.is_err(), "result is error: {result:#?}");
+        let result = a.list_seen_episodes(user_id, &friends());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        let seen_episodes = result.unwrap();
         assert_eq!(
-            result.unwrap(),
-            vec!(Episode::from("s01e01"), Episode::from("s01e02"),)
+            seen_episodes
+                .iter()
+                .map(SeenEpisode::episode)
+                .collect::<Vec<_>>(),
+            vec![
+                &Episode::try_from("s03e05").unwrap(),
+                &Episode::try_from("s01e01").unwrap()
+            ]
         );
+        assert!(seen_episodes.iter().all(|seen_episode| {
+            seen_episode.seen_at().is_some() && seen_episode.source() == SeenEpisodeSource::Bot
+        }));
     }
 
     #[test]
-    fn application_select_next_episode_fn_returns_any_episode_at_all() {
+    fn application_clear_seen_episodes_fn_forgets_only_given_user() {
         let a = build_application();
 
-        let result = a.select_next_episode(&Vec::new());
-        assert!(!result.is_err(), "result is error: {result:#?}");
-        assert!(EPISODES.contains(&result.unwrap().code()))
+        a.mark_seen(
+            UserID::new(1),
+            &friends(),
+            Episode::try_from("s01e01").unwrap(),
+        )
+        .unwrap();
+        a.mark_seen(
+            UserID::new(2),
+            &friends(),
+            Episode::try_from("s01e01").unwrap(),
+        )
+        .unwrap();
+
+        let result = a.clear_seen_episodes(UserID::new(1), &friends());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        assert_eq!(
+            a.list_seen_episodes(UserID::new(1), &friends()).unwrap(),
+            Vec::new()
+        );
+        assert_eq!(
+            a.list_seen_episodes(UserID::new(2), &friends())
+                .unwrap()
+                .len(),
+            1
+        );
     }
 
     #[test]
-    fn application_select_next_episode_fn_returns_error_if_there_is_no_unseen_episodes() {
+    fn application_mark_seen_fn_does_not_lose_concurrent_updates() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let a = new(
+            Arc::new(storage::file::new(temp_dir.path().to_path_buf())),
+            Catalogue::builtin(),
+        );
+        let user_id = UserID::new(317);
+
+        thread::scope(|scope| {
+            for &code in EPISODES.iter().take(50) {
+                let a = &a;
+                scope.spawn(move || {
+                    a.mark_seen(user_id, &friends(), Episode::try_from(code).unwrap())
+                        .unwrap()
+                });
+            }
+        });
+
+        let mut seen_codes: Vec<String> = a
+            .list_seen_episodes(user_id, &friends())
+            .unwrap()
+            .iter()
+            .map(|seen_episode| seen_episode.episode().code().to_string())
+            .collect();
+        seen_codes.sort();
+
+        assert_eq!(seen_codes, EPISODES[..50]);
+    }
+
+    #[test]
+    fn application_user_lock_fn_evicts_lock_nobody_holds() {
         let a = build_application();
 
-        let all_episodes: Vec<Episode> = EPISODES.iter().map(|s| Episode::from(s)).collect();
+        thread::scope(|scope| {
+            for user_id in 0..10 {
+                let a = &a;
+                scope.spawn(move || {
+                    a.get_next_episode(UserID::new(user_id), &friends())
+                        .unwrap()
+                });
+            }
+        });
+
+        assert!(a.user_locks.lock().unwrap().is_empty());
+
+        let user_lock = a.user_lock(UserID::new(317));
+        let other_user_lock = a.user_lock(UserID::new(317));
+        drop(user_lock);
+        assert_eq!(a.user_locks.lock().unwrap().len(), 1);
+        drop(other_user_lock);
+        assert!(a.user_locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn application_get_next_episode_fn_picks_from_given_catalogue() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let catalogue_path = temp_dir.path().join("catalogue.json");
+        std::fs::write(&catalogue_path, r#"{"episodes": [{"code": "s11e01"}]}"#).unwrap();
+        let catalogue = catalogue::load(Some(&catalogue_path)).unwrap();
+        let a = new(Arc::new(storage::memory::new()), catalogue);
 
-        let result = a.select_next_episode(&all_episodes);
+        let result = a.get_next_episode(UserID::new(317), &friends());
 
-        assert!(matches!(result, Err(Error::NoUnseenEpisodes)));
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Episode::try_from("s11e01").unwrap());
     }
 
     #[test]
-    fn application_save_db_to_file_fn_saves_empty_list_to_file() {
+    fn application_find_episode_fn_rejects_malformed_and_unknown_codes() {
         let a = build_application();
 
-        let mut tmpfile = NamedTempFile::new().unwrap();
+        assert!(a.find_episode(&friends(), "s01e01").is_ok());
+        assert!(matches!(
+            a.find_episode(&friends(), "mark_seen=xyz"),
+            Err(Error::EpisodeParseError(_))
+        ));
+        assert!(matches!(
+            a.find_episode(&friends(), "s42e01"),
+            Err(Error::UnknownEpisode(_))
+        ));
+    }
+
+    #[test]
+    fn application_mark_seen_fn_rejects_episode_missing_from_catalogue() {
+        let a = build_application();
+        let user_id = UserID::new(317);
 
-        let result = a.save_db_to_file(Vec::new(), &tmpfile.path());
-        assert!(!result.is_err(), "result is error: {result:#?}");
+        let result = a.mark_seen(user_id, &friends(), Episode::try_from("s42e01").unwrap());
 
-        let mut tmpfile_content = String::new();
-        tmpfile.read_to_string(&mut tmpfile_content).unwrap();
-        assert_eq!(tmpfile_content, "");
+        assert!(matches!(result, Err(Error::UnknownEpisode(_))));
+        assert_eq!(
+            a.list_seen_episodes(user_id, &friends()).unwrap(),
+            Vec::new()
+        );
     }
 
     #[test]
-    fn application_save_db_to_file_fn_saves_non_empty_list_to_file_in_reverse_order() {
+    fn application_active_show_fn_returns_default_show_until_user_selects_another() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+        let office = ShowID::new("the-office");
+
+        assert_eq!(a.active_show(user_id).unwrap().id(), &friends());
+
+        let result = a.select_show(user_id, &office);
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        assert_eq!(a.active_show(user_id).unwrap().id(), &office);
+        assert_eq!(a.active_show(UserID::new(1)).unwrap().id(), &friends());
+    }
+
+    #[test]
+    fn application_select_show_fn_rejects_unknown_show() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+
+        let result = a.select_show(user_id, &ShowID::new("himym"));
+
+        assert!(matches!(result, Err(Error::UnknownShow(_))));
+        assert_eq!(a.active_show(user_id).unwrap().id(), &friends());
+    }
+
+    #[test]
+    fn application_active_show_fn_falls_back_to_default_if_show_was_removed() {
         let a = build_application();
+        let user_id = UserID::new(317);
+        let settings = UserSettings {
+            show: Some(ShowID::new("the-office")),
+            ..UserSettings::default()
+        };
+        a.store.save_settings(&user_id, &settings).unwrap();
 
-        let mut tmpfile = NamedTempFile::new().unwrap();
+        assert_eq!(a.active_show(user_id).unwrap().id(), &friends());
+    }
 
-        let seen_episodes = vec![Episode::from("s01e01"), Episode::from("s01e02")];
+    #[test]
+    fn application_keeps_seen_episodes_of_each_show_separately() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+        let office = ShowID::new("the-office");
+
+        a.mark_seen(user_id, &friends(), Episode::try_from("s01e01").unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            a.get_next_episode(user_id, &friends()),
+            Err(Error::NoUnseenEpisodes)
+        ));
+        assert_eq!(
+            a.get_next_episode(user_id, &office).unwrap(),
+            Episode::try_from("s02e01").unwrap()
+        );
+        assert_eq!(a.list_seen_episodes(user_id, &office).unwrap(), Vec::new());
+    }
 
-        let result = a.save_db_to_file(seen_episodes, &tmpfile.path());
-        assert!(!result.is_err(), "result is error: {result:#?}");
+    #[test]
+    fn application_mark_seen_fn_rejects_episode_of_another_show() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+
+        let result = a.mark_seen(
+            user_id,
+            &ShowID::new("the-office"),
+            Episode::try_from("s01e01").unwrap(),
+        );
 
-        let mut tmpfile_content = String::new();
-        tmpfile.read_to_string(&mut tmpfile_content).unwrap();
-        assert_eq!(tmpfile_content, "s01e02\ns01e01\n");
+        assert!(matches!(result, Err(Error::UnknownEpisode(_))));
     }
 
     #[test]
-    fn application_create_directory_if_not_exists_fn_creates_multiple_directories() {
+    fn application_get_next_episode_fn_picks_only_from_filtered_seasons() {
         let a = build_application();
+        let user_id = UserID::new(317);
+
+        let result =
+            a.set_season_filter(user_id, &friends(), Some(SeasonFilter::new(3, 4).unwrap()));
+        assert!(result.is_ok(), "result is error: {result:#?}");
 
-        let tmpdir = TempDir::new().unwrap();
+        for _ in 0..20 {
+            let episode = a.get_next_episode(user_id, &friends()).unwrap();
+            assert!((3..=4).contains(&episode.season()), "episode: {episode:#?}");
+        }
+        assert_eq!(
+            a.season_filter(user_id, &friends()).unwrap(),
+            Some(SeasonFilter::new(3, 4).unwrap())
+        );
+        assert_eq!(a.season_filter(UserID::new(1), &friends()).unwrap(), None);
+    }
 
-        let path = tmpdir.path().join("some/path");
-        eprintln!("{}", path.to_string_lossy());
-        assert!(!path.exists());
+    #[test]
+    fn application_get_next_episode_fn_returns_error_if_filtered_seasons_are_seen() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        a.set_season_filter(user_id, &friends(), Some(SeasonFilter::single(1).unwrap()))
+            .unwrap();
 
-        let result = a.create_directory_if_not_exists(&path);
-        assert!(!result.is_err(), "result is error: {result:#?}");
-        assert!(path.exists());
+        for &code in EPISODES.iter().filter(|code| code.starts_with("s01")) {
+            a.mark_seen(user_id, &friends(), Episode::try_from(code).unwrap())
+                .unwrap();
+        }
+
+        assert!(matches!(
+            a.get_next_episode(user_id, &friends()),
+            Err(Error::NoUnseenEpisodes)
+        ));
+
+        a.set_season_filter(user_id, &friends(), None).unwrap();
+
+        assert_eq!(a.season_filter(user_id, &friends()).unwrap(), None);
+        assert!(a.get_next_episode(user_id, &friends()).unwrap().season() > 1);
     }
 
     #[test]
-    fn application_create_directory_if_not_exists_fn_does_not_error_if_directories_already_exists()
-    {
+    fn application_set_season_filter_fn_rejects_seasons_without_episodes() {
         let a = build_application();
+        let user_id = UserID::new(317);
+        let season_filter = SeasonFilter::single(2).unwrap();
+        a.set_season_filter(user_id, &friends(), Some(season_filter))
+            .unwrap();
+
+        let result = a.set_season_filter(
+            user_id,
+            &friends(),
+            Some(SeasonFilter::new(11, 12).unwrap()),
+        );
 
-        // Setup temp directory
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let test_path = temp_dir.path().join("existing").join("subdirectory");
+        assert!(matches!(result, Err(Error::SeasonFilterError(_))));
+        assert_eq!(
+            a.season_filter(user_id, &friends()).unwrap(),
+            Some(season_filter)
+        );
+    }
 
-        // First create the directory structure
-        fs::create_dir_all(&test_path).expect("Failed to create initial directory structure");
+    #[test]
+    fn application_update_settings_fn_saves_changed_settings() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+
+        let result = a.update_settings(user_id, |settings| {
+            settings.language = Language::English;
+            settings.show_keyboard = false;
+        });
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let settings = a.settings(user_id).unwrap();
+        assert_eq!(settings, result.unwrap());
+        assert_eq!(settings.language, Language::English);
+        assert!(!settings.show_keyboard);
+        assert_eq!(a.settings(UserID::new(1)).unwrap(), UserSettings::default());
+    }
 
-        // Test the function - should not error even though dir exists
-        let result = a.create_directory_if_not_exists(&test_path);
+    #[test]
+    fn application_get_next_episode_fn_starts_new_cycle_when_auto_reset_is_enabled() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+        let office = ShowID::new("the-office");
+        a.mark_seen(user_id, &friends(), Episode::try_from("s01e01").unwrap())
+            .unwrap();
+        a.mark_seen(user_id, &office, Episode::try_from("s02e01").unwrap())
+            .unwrap();
+
+        a.update_settings(user_id, |settings| settings.auto_reset = true)
+            .unwrap();
+        let result = a.get_next_episode(user_id, &friends());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Episode::try_from("s01e01").unwrap());
+        assert_eq!(
+            a.list_seen_episodes(user_id, &friends()).unwrap(),
+            Vec::new()
+        );
+        assert_eq!(a.completed_cycles(user_id, &friends()).unwrap(), 1);
+        assert_eq!(a.list_seen_episodes(user_id, &office).unwrap().len(), 1);
+        assert_eq!(a.completed_cycles(user_id, &office).unwrap(), 0);
+
+        a.mark_seen(user_id, &friends(), Episode::try_from("s01e01").unwrap())
+            .unwrap();
+        a.get_next_episode(user_id, &friends()).unwrap();
+        assert_eq!(a.completed_cycles(user_id, &friends()).unwrap(), 2);
+    }
 
-        // Verify
+    #[test]
+    fn application_get_next_episode_fn_does_not_start_over_if_only_filtered_seasons_are_seen() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        a.update_settings(user_id, |settings| settings.auto_reset = true)
+            .unwrap();
+        a.set_season_filter(user_id, &friends(), Some(SeasonFilter::single(1).unwrap()))
+            .unwrap();
+        for &code in EPISODES.iter().filter(|code| code.starts_with("s01")) {
+            a.mark_seen(user_id, &friends(), Episode::try_from(code).unwrap())
+                .unwrap();
+        }
+
+        let result = a.get_next_episode(user_id, &friends());
+
+        assert!(matches!(result, Err(Error::NoUnseenEpisodes)));
         assert!(
-            result.is_ok(),
-            "Function should not error when directory already exists"
+            !a.list_seen_episodes(user_id, &friends())
+                .unwrap()
+                .is_empty()
         );
+    }
 
-        // Additional verification that directory still exists
-        assert!(
-            test_path.exists(),
-            "Directory should still exist after function call"
+    #[test]
+    fn application_get_next_episode_fn_uses_strategy_from_settings() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+        a.mark_seen(user_id, &friends(), Episode::try_from("s01e01").unwrap())
+            .unwrap();
+        assert!(matches!(
+            a.get_next_episode(user_id, &friends()),
+            Err(Error::NoUnseenEpisodes)
+        ));
+
+        a.update_settings(user_id, |settings| {
+            settings.selection_strategy = SelectionStrategyKind::LeastRecent;
+        })
+        .unwrap();
+        let result = a.get_next_episode(user_id, &friends());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Episode::try_from("s01e01").unwrap());
+        assert_eq!(a.completed_cycles(user_id, &friends()).unwrap(), 0);
+    }
+
+    #[test]
+    fn application_get_next_episode_fn_does_not_repeat_recent_offers() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+
+        let picks: Vec<Episode> = (0..=RECENT_OFFERS_LIMIT)
+            .map(|_| a.get_next_episode(user_id, &friends()).unwrap())
+            .collect();
+
+        let unique_picks: HashSet<&Episode> = picks.iter().collect();
+        assert_eq!(unique_picks.len(), picks.len(), "picks: {picks:?}");
+        let recent_offers = a
+            .store
+            .load_recent_offers(&user_id, &friends(), RECENT_OFFERS_LIMIT + 1)
+            .unwrap();
+        assert_eq!(
+            recent_offers
+                .iter()
+                .map(|offer| offer.episode().clone())
+                .collect::<Vec<_>>(),
+            picks
         );
+    }
+
+    #[test]
+    fn application_get_next_episode_fn_repeats_offer_if_there_is_nothing_else() {
+        let a = build_application_with_two_shows();
+        let user_id = UserID::new(317);
+        let episode = Episode::try_from("s01e01").unwrap();
+
+        assert_eq!(a.get_next_episode(user_id, &friends()).unwrap(), episode);
+        let result = a.get_next_episode(user_id, &friends());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), episode);
+    }
+
+    #[test]
+    fn application_unmark_seen_fn_removes_only_given_episode() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        for code in ["s01e01", "s01e02"] {
+            a.mark_seen(user_id, &friends(), Episode::try_from(code).unwrap())
+                .unwrap();
+        }
+
+        let result = a.unmark_seen(user_id, &friends(), &Episode::try_from("s01e01").unwrap());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert!(result.unwrap());
+        let seen_episodes = a.list_seen_episodes(user_id, &friends()).unwrap();
+        assert_eq!(seen_episodes.len(), 1);
+        assert_eq!(seen_episodes[0].episode().code(), "s01e02");
         assert!(
-            test_path.is_dir(),
-            "Path should still be a directory after function call"
+            !a.unmark_seen(user_id, &friends(), &Episode::try_from("s01e01").unwrap())
+                .unwrap()
         );
     }
 
     #[test]
-    fn application_clear_seen_episodes_fn_removes_existing_file() {
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let a = Application {
-            storage_path: temp_dir.path().to_path_buf(),
-        };
+    fn application_undo_last_mark_fn_removes_most_recent_mark() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        for code in ["s01e01", "s01e02"] {
+            a.mark_seen(user_id, &friends(), Episode::try_from(code).unwrap())
+                .unwrap();
+        }
 
-        let user_id = UserID(317);
-        let test_file_path = a.build_user_storage_path(&user_id);
-        fs::create_dir_all(test_file_path.parent().unwrap())
-            .expect("не удалось создать папку для тестового файла");
-        File::create(&test_file_path)
-            .expect("не удалось создать тестовый файл")
-            .write_all(b"test data")
-            .expect("не удалось записать в тестовый файл");
+        let result = a.undo_last_mark(user_id, &friends());
 
-        let result = a.clear_seen_episodes(user_id);
-        assert!(!result.is_err(), "result is error: {result:#?}");
-        assert!(!test_file_path.exists(), "File should be deleted");
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Some(Episode::try_from("s01e02").unwrap()));
+        assert_eq!(
+            a.undo_last_mark(user_id, &friends()).unwrap(),
+            Some(Episode::try_from("s01e01").unwrap())
+        );
+        assert_eq!(a.undo_last_mark(user_id, &friends()).unwrap(), None);
     }
 
     #[test]
-    fn application_clear_seen_episodes_fn_returns_no_error_if_file_does_not_exist() {
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let app = Application {
-            storage_path: temp_dir.path().to_path_buf(),
-        };
+    fn application_mark_seen_episodes_fn_marks_only_unseen_episodes() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        a.mark_seen(user_id, &friends(), Episode::try_from("s01e02").unwrap())
+            .unwrap();
+
+        let result = a.mark_seen_episodes(
+            user_id,
+            &friends(),
+            &"s01e01-s01e03 s01e01".parse().unwrap(),
+        );
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Episode::try_from("s01e01").unwrap(),
+                Episode::try_from("s01e03").unwrap()
+            ]
+        );
+        let seen_episodes = a.list_seen_episodes(user_id, &friends()).unwrap();
+        assert_eq!(seen_episodes.len(), 3);
+        assert_eq!(seen_episodes[2].source(), SeenEpisodeSource::Manual);
+    }
+
+    #[test]
+    fn application_mark_seen_episodes_fn_marks_whole_season() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        let first_season = EPISODES
+            .iter()
+            .filter(|code| code.starts_with("s01"))
+            .count();
+
+        let result = a.mark_seen_episodes(user_id, &friends(), &"s01".parse().unwrap());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap().len(), first_season);
+        assert_eq!(
+            a.list_seen_episodes(user_id, &friends()).unwrap().len(),
+            first_season
+        );
+    }
+
+    #[test]
+    fn application_mark_seen_episodes_fn_changes_nothing_if_any_episode_is_unknown() {
+        let a = build_application();
+        let user_id = UserID::new(317);
 
-        // Test with non-existent user
-        let result = app.clear_seen_episodes(UserID::new(999));
+        let result = a.mark_seen_episodes(user_id, &friends(), &"s01e01 s42e01".parse().unwrap());
+
+        assert!(matches!(result, Err(Error::UnknownEpisode(_))));
+        assert_eq!(
+            a.list_seen_episodes(user_id, &friends()).unwrap(),
+            Vec::new()
+        );
+    }
 
-        assert!(!result.is_err(), "result is error: {result:#?}");
+    #[test]
+    fn application_unmark_seen_episodes_fn_removes_marks_of_given_episodes() {
+        let a = build_application();
+        let user_id = UserID::new(317);
+        a.mark_seen_episodes(user_id, &friends(), &"s01e01-s01e04".parse().unwrap())
+            .unwrap();
+
+        let result = a.unmark_seen_episodes(
+            user_id,
+            &friends(),
+            &"s01e02 s01e03 s02e01".parse().unwrap(),
+        );
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Episode::try_from("s01e02").unwrap(),
+                Episode::try_from("s01e03").unwrap()
+            ]
+        );
+        let seen_codes: Vec<String> = a
+            .list_seen_episodes(user_id, &friends())
+            .unwrap()
+            .iter()
+            .map(|seen_episode| seen_episode.episode().code().to_string())
+            .collect();
+        assert_eq!(seen_codes, vec!["s01e01", "s01e04"]);
     }
 }
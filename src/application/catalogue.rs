@@ -0,0 +1,630 @@
+use super::{Episode, EpisodeMetadata, Error, episodes::EPISODES};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize, de::IgnoredAny};
+use std::{collections::HashSet, fmt::Display, fs, path::Path};
+
+/// Сериал, с которым бот работал до поддержки нескольких сериалов.
+/// К нему относятся все данные, сохранённые до этого.
+pub const DEFAULT_SHOW_ID: &str = "friends";
+
+/// Идентификатор попадает в пути к файлам и в данные колбеков, поэтому он короткий
+/// и состоит только из латинских букв в нижнем регистре, цифр, `_` и `-`.
+const MAX_SHOW_ID_LEN: usize = 32;
+
+/// Загрузить каталог сериалов из JSON-файла. Без файла используется встроенный список
+/// серий сериала Друзья.
+pub fn load(path: Option<&Path>) -> Result<Catalogue, Error> {
+    match path {
+        Some(path) => Catalogue::from_file(path),
+        None => Ok(Catalogue::builtin()),
+    }
+}
+
+/// Сериалы, из серий которых бот выбирает следующую. По умолчанию используется сериал
+/// [`DEFAULT_SHOW_ID`], поэтому без него каталог не загружается.
+#[derive(Debug)]
+pub struct Catalogue {
+    shows: Vec<Show>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ShowID(String);
+
+impl ShowID {
+    pub fn new(show_id: &str) -> Self {
+        Self(show_id.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for ShowID {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHOW_ID)
+    }
+}
+
+impl Display for ShowID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct Show {
+    id: ShowID,
+    name: String,
+    watch_url_template: Option<String>,
+    episodes: Vec<Episode>,
+}
+
+/// Файл каталога содержит либо список сериалов, либо, как раньше, список серий
+/// одного сериала Друзья. Формат определяется по ключу `shows`, а затем файл разбирается
+/// уже как конкретный формат, чтобы ошибка указывала на место в файле.
+#[derive(Deserialize)]
+struct CatalogueFormat {
+    shows: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+struct ShowsFile {
+    shows: Vec<ShowFileEntry>,
+}
+
+#[derive(Deserialize)]
+struct EpisodesFile {
+    episodes: Vec<EpisodeFileEntry>,
+}
+
+#[derive(Deserialize)]
+struct ShowFileEntry {
+    id: String,
+    name: String,
+    watch_url_template: Option<String>,
+    episodes: Vec<EpisodeFileEntry>,
+}
+
+#[derive(Deserialize)]
+struct EpisodeFileEntry {
+    code: String,
+    title: Option<String>,
+    localized_title: Option<String>,
+    air_date: Option<NaiveDate>,
+    runtime: Option<u16>,
+    synopsis: Option<String>,
+}
+
+impl ShowFileEntry {
+    fn into_show(self) -> Result<Show, Error> {
+        let is_valid_id = !self.id.is_empty()
+            && self.id.len() <= MAX_SHOW_ID_LEN
+            && self
+                .id
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-');
+        if !is_valid_id {
+            return Err(Error::CatalogueError(format!(
+                "идентификатор сериала должен состоять из латинских букв в нижнем регистре, цифр, `_` и `-` и быть не длиннее {MAX_SHOW_ID_LEN} символов: id={}",
+                self.id
+            )));
+        }
+
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            return Err(Error::CatalogueError(format!(
+                "у сериала нет названия: id={}",
+                self.id
+            )));
+        }
+
+        let episodes = parse_episodes(self.episodes)
+            .map_err(|err| Error::CatalogueError(format!("сериал {}: {err}", self.id)))?;
+
+        Ok(Show {
+            id: ShowID(self.id),
+            name,
+            watch_url_template: non_blank(self.watch_url_template),
+            episodes,
+        })
+    }
+}
+
+impl EpisodeFileEntry {
+    fn into_episode(self) -> Result<Episode, Error> {
+        let episode = Episode::try_from(self.code.as_str())
+            .map_err(|err| Error::CatalogueError(format!("некорректный код серии: {err}")))?;
+
+        if self.runtime == Some(0) {
+            return Err(Error::CatalogueError(format!(
+                "продолжительность серии должна быть больше нуля: code={}",
+                self.code
+            )));
+        }
+
+        let metadata = EpisodeMetadata {
+            title: non_blank(self.title),
+            localized_title: non_blank(self.localized_title),
+            air_date: self.air_date,
+            runtime: self.runtime,
+            synopsis: non_blank(self.synopsis),
+        };
+
+        if metadata == EpisodeMetadata::default() {
+            return Ok(episode);
+        }
+
+        Ok(episode.with_metadata(metadata))
+    }
+}
+
+fn parse_episodes(entries: Vec<EpisodeFileEntry>) -> Result<Vec<Episode>, Error> {
+    if entries.is_empty() {
+        return Err(Error::CatalogueError(String::from(
+            "в каталоге нет ни одной серии",
+        )));
+    }
+
+    let mut codes = HashSet::new();
+    let mut episodes = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let episode = entry.into_episode()?;
+
+        if !codes.insert(episode.code().to_string()) {
+            return Err(Error::CatalogueError(format!(
+                "серия встречается в каталоге дважды: code={}",
+                episode.code()
+            )));
+        }
+
+        episodes.push(episode);
+    }
+
+    Ok(episodes)
+}
+
+impl Catalogue {
+    pub fn builtin() -> Self {
+        Self {
+            shows: vec![Show {
+                id: ShowID::default(),
+                name: String::from("Друзья"),
+                watch_url_template: None,
+                episodes: EPISODES
+                    .iter()
+                    .map(|&code| {
+                        Episode::try_from(code).expect("встроенный список серий корректен")
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).map_err(|err| {
+            Error::CatalogueError(format!(
+                "не удалось прочитать файл {}: {err}",
+                path.display()
+            ))
+        })?;
+
+        Self::from_json(&content)
+    }
+
+    pub(crate) fn from_json(content: &str) -> Result<Self, Error> {
+        let json_error =
+            |err: serde_json::Error| Error::CatalogueError(format!("некорректный JSON: {err}"));
+        let format: CatalogueFormat = serde_json::from_str(content).map_err(json_error)?;
+
+        let shows = match format.shows {
+            Some(_) => {
+                let file: ShowsFile = serde_json::from_str(content).map_err(json_error)?;
+                file.shows
+                    .into_iter()
+                    .map(ShowFileEntry::into_show)
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            None => {
+                let file: EpisodesFile = serde_json::from_str(content).map_err(json_error)?;
+                vec![Show {
+                    id: ShowID::default(),
+                    name: String::from("Друзья"),
+                    watch_url_template: None,
+                    episodes: parse_episodes(file.episodes)?,
+                }]
+            }
+        };
+
+        if shows.is_empty() {
+            return Err(Error::CatalogueError(String::from(
+                "в каталоге нет ни одного сериала",
+            )));
+        }
+
+        let mut ids = HashSet::new();
+        for show in &shows {
+            if !ids.insert(&show.id) {
+                return Err(Error::CatalogueError(format!(
+                    "сериал встречается в каталоге дважды: id={}",
+                    show.id
+                )));
+            }
+        }
+
+        // к этому сериалу относится вся история, сохранённая до поддержки нескольких сериалов
+        if !shows.iter().any(|show| show.id.as_str() == DEFAULT_SHOW_ID) {
+            return Err(Error::CatalogueError(format!(
+                "в каталоге нет сериала по умолчанию: id={DEFAULT_SHOW_ID}"
+            )));
+        }
+
+        Ok(Self { shows })
+    }
+
+    pub fn shows(&self) -> &[Show] {
+        &self.shows
+    }
+
+    pub fn default_show(&self) -> &Show {
+        self.shows
+            .iter()
+            .find(|show| show.id.as_str() == DEFAULT_SHOW_ID)
+            .expect("каталог без сериала по умолчанию не загружается")
+    }
+
+    pub fn show(&self, show_id: &ShowID) -> Result<&Show, Error> {
+        self.shows
+            .iter()
+            .find(|show| &show.id == show_id)
+            .ok_or_else(|| Error::UnknownShow(show_id.to_string()))
+    }
+}
+
+impl Show {
+    pub fn id(&self) -> &ShowID {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Собственный шаблон ссылки для просмотра, если он указан в каталоге.
+    pub fn watch_url_template(&self) -> Option<&str> {
+        self.watch_url_template.as_deref()
+    }
+
+    pub fn episodes(&self) -> &[Episode] {
+        &self.episodes
+    }
+
+    /// Номера сезонов, в которых есть серии, по возрастанию.
+    pub fn seasons(&self) -> Vec<u8> {
+        let mut seasons: Vec<u8> = self.episodes.iter().map(Episode::season).collect();
+        seasons.sort();
+        seasons.dedup();
+
+        seasons
+    }
+
+    pub fn contains(&self, episode: &Episode) -> bool {
+        self.episodes.contains(episode)
+    }
+
+    /// Найти серию по коду. Возвращает серию вместе с её описанием из каталога.
+    pub fn find(&self, code: &str) -> Result<&Episode, Error> {
+        let episode = Episode::try_from(code)?;
+
+        self.episodes
+            .iter()
+            .find(|&candidate| candidate == &episode)
+            .ok_or_else(|| Error::UnknownEpisode(code.to_string()))
+    }
+}
+
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn load_fn_returns_builtin_catalogue_without_path() {
+        let result = load(None);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap().default_show().episodes().len(),
+            EPISODES.len()
+        );
+    }
+
+    #[test]
+    fn load_fn_reads_catalogue_from_file() {
+        let tmpfile = NamedTempFile::new().unwrap();
+        fs::write(
+            tmpfile.path(),
+            r#"{"episodes": [{"code": "s01e01"}, {"code": "s01e02"}]}"#,
+        )
+        .unwrap();
+
+        let result = load(Some(tmpfile.path()));
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap().default_show().episodes(),
+            [
+                Episode::try_from("s01e01").unwrap(),
+                Episode::try_from("s01e02").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn load_fn_returns_error_for_missing_file() {
+        let result = load(Some(Path::new("non_existing_catalogue.json")));
+
+        assert!(matches!(result, Err(Error::CatalogueError(_))));
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_invalid_json() {
+        let result = Catalogue::from_json(r#"{"episodes": "#);
+
+        assert!(matches!(result, Err(Error::CatalogueError(_))));
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_reports_where_shows_file_is_invalid() {
+        let result = Catalogue::from_json(
+            r#"{"shows": [{"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01", "runtime": "long"}]}]}"#,
+        );
+
+        let Err(Error::CatalogueError(message)) = result else {
+            panic!("unexpected result: {result:#?}");
+        };
+        assert!(message.contains("expected u16"), "message: {message}");
+        assert!(message.contains("line 1 column"), "message: {message}");
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_reports_where_episodes_file_is_invalid() {
+        let result = Catalogue::from_json("{\"episodes\": [\n{\"title\": \"Пилот\"}]}");
+
+        let Err(Error::CatalogueError(message)) = result else {
+            panic!("unexpected result: {result:#?}");
+        };
+        assert!(
+            message.contains("missing field `code`"),
+            "message: {message}"
+        );
+        assert!(message.contains("line 2 column"), "message: {message}");
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_empty_catalogue() {
+        let result = Catalogue::from_json(r#"{"episodes": []}"#);
+
+        assert!(matches!(result, Err(Error::CatalogueError(_))));
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_malformed_codes() {
+        for code in [
+            "s1e1", "x01e01", "s01x01", "s0ae01", "s01e0б", "s0бe0", "s01e01 ",
+        ] {
+            let result =
+                Catalogue::from_json(&format!(r#"{{"episodes": [{{"code": "{code}"}}]}}"#));
+
+            assert!(
+                matches!(result, Err(Error::CatalogueError(_))),
+                "code {code:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_duplicate_codes() {
+        let result =
+            Catalogue::from_json(r#"{"episodes": [{"code": "s01e01"}, {"code": "s01e01"}]}"#);
+
+        assert!(matches!(result, Err(Error::CatalogueError(_))));
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_reads_episode_metadata() {
+        let result = Catalogue::from_json(
+            r#"{"episodes": [{
+                "code": "s01e01",
+                "title": "The One Where Monica Gets a Roommate",
+                "localized_title": "Эпизод, где Моника берёт новую соседку",
+                "air_date": "1994-09-22",
+                "runtime": 22,
+                "synopsis": "  "
+            }, {"code": "s01e02"}]}"#,
+        );
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let catalogue = result.unwrap();
+        assert_eq!(
+            catalogue.default_show().episodes()[0].metadata(),
+            Some(&EpisodeMetadata {
+                title: Some(String::from("The One Where Monica Gets a Roommate")),
+                localized_title: Some(String::from("Эпизод, где Моника берёт новую соседку")),
+                air_date: NaiveDate::from_ymd_opt(1994, 9, 22),
+                runtime: Some(22),
+                synopsis: None,
+            })
+        );
+        assert_eq!(catalogue.default_show().episodes()[1].metadata(), None);
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_invalid_metadata() {
+        for entry in [
+            r#"{"code": "s01e01", "air_date": "22.09.1994"}"#,
+            r#"{"code": "s01e01", "runtime": 0}"#,
+            r#"{"code": "s01e01", "runtime": -5}"#,
+        ] {
+            let result = Catalogue::from_json(&format!(r#"{{"episodes": [{entry}]}}"#));
+
+            assert!(
+                matches!(result, Err(Error::CatalogueError(_))),
+                "entry {entry} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn catalogue_builtin_fn_parses_every_episode() {
+        let catalogue = Catalogue::builtin();
+
+        assert_eq!(catalogue.shows().len(), 1);
+        assert_eq!(catalogue.default_show().id(), &ShowID::default());
+        assert_eq!(catalogue.default_show().episodes().len(), 234);
+    }
+
+    #[test]
+    fn show_find_fn_works_as_expected() {
+        let catalogue = Catalogue::builtin();
+        let catalogue = catalogue.default_show();
+
+        let result = catalogue.find("s05e14");
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), &Episode::try_from("s05e14").unwrap());
+
+        assert!(matches!(
+            catalogue.find("s10e18"),
+            Err(Error::UnknownEpisode(_))
+        ));
+        assert!(matches!(
+            catalogue.find("xyz"),
+            Err(Error::EpisodeParseError(_))
+        ));
+    }
+
+    #[test]
+    fn show_seasons_fn_returns_each_season_once() {
+        let catalogue = Catalogue::from_json(
+            r#"{"episodes": [{"code": "s02e01"}, {"code": "s01e02"}, {"code": "s02e02"}, {"code": "s01e01"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(catalogue.default_show().seasons(), vec![1, 2]);
+        assert_eq!(
+            Catalogue::builtin().default_show().seasons(),
+            (1..=10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn show_contains_fn_works_as_expected() {
+        let catalogue = Catalogue::builtin();
+        let catalogue = catalogue.default_show();
+
+        assert!(catalogue.contains(&Episode::try_from("s10e17").unwrap()));
+        assert!(!catalogue.contains(&Episode::try_from("s10e18").unwrap()));
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_reads_several_shows() {
+        let result = Catalogue::from_json(
+            r#"{"shows": [
+                {
+                    "id": "the-office",
+                    "name": "Офис",
+                    "watch_url_template": "https://example.com/office/{season}",
+                    "episodes": [{"code": "s01e01"}, {"code": "s01e02"}]
+                },
+                {"id": "himym", "name": "Как я встретил вашу маму", "episodes": [{"code": "s01e01"}]},
+                {"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01"}]}
+            ]}"#,
+        );
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let catalogue = result.unwrap();
+        assert_eq!(catalogue.shows().len(), 3);
+
+        let himym = catalogue.show(&ShowID::new("himym")).unwrap();
+        assert_eq!(himym.name(), "Как я встретил вашу маму");
+        assert_eq!(himym.watch_url_template(), None);
+        assert_eq!(himym.episodes().len(), 1);
+        assert_eq!(
+            catalogue
+                .show(&ShowID::new("the-office"))
+                .unwrap()
+                .watch_url_template(),
+            Some("https://example.com/office/{season}")
+        );
+
+        assert!(matches!(
+            catalogue.show(&ShowID::new("dark")),
+            Err(Error::UnknownShow(_))
+        ));
+    }
+
+    #[test]
+    fn catalogue_default_show_fn_returns_friends_even_if_it_is_not_first() {
+        let catalogue = Catalogue::from_json(
+            r#"{"shows": [
+                {"id": "the-office", "name": "Офис", "episodes": [{"code": "s01e01"}]},
+                {"id": "friends", "name": "Друзья", "episodes": [{"code": "s02e01"}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(catalogue.default_show().id(), &ShowID::default());
+        assert_eq!(
+            catalogue.default_show().episodes(),
+            &[Episode::try_from("s02e01").unwrap()]
+        );
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_catalogue_without_default_show() {
+        let result = Catalogue::from_json(
+            r#"{"shows": [{"id": "the-office", "name": "Офис", "episodes": [{"code": "s01e01"}]}]}"#,
+        );
+
+        assert!(matches!(result, Err(Error::CatalogueError(_))));
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_reads_episodes_only_file_as_default_show() {
+        let result = Catalogue::from_json(r#"{"episodes": [{"code": "s01e01"}]}"#);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap().default_show().id(), &ShowID::default());
+    }
+
+    #[test]
+    fn catalogue_from_json_fn_rejects_invalid_shows() {
+        for shows in [
+            r#"[]"#,
+            r#"[{"id": "Friends", "name": "Друзья", "episodes": [{"code": "s01e01"}]}]"#,
+            r#"[{"id": "a:b", "name": "Друзья", "episodes": [{"code": "s01e01"}]}]"#,
+            r#"[{"id": "", "name": "Друзья", "episodes": [{"code": "s01e01"}]}]"#,
+            r#"[{"id": "friends", "name": " ", "episodes": [{"code": "s01e01"}]}]"#,
+            r#"[{"id": "friends", "name": "Друзья", "episodes": []}]"#,
+            r#"[
+                {"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01"}]},
+                {"id": "friends", "name": "Друзья 2", "episodes": [{"code": "s01e01"}]}
+            ]"#,
+        ] {
+            let result = Catalogue::from_json(&format!(r#"{{"shows": {shows}}}"#));
+
+            assert!(
+                matches!(result, Err(Error::CatalogueError(_))),
+                "shows {shows} should be rejected"
+            );
+        }
+    }
+}
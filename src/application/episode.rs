@@ -1,21 +1,40 @@
-#[derive(PartialEq, Debug, Eq, Hash)]
+use super::Error;
+use chrono::NaiveDate;
+use std::{
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// Серия определяется своим кодом, поэтому серии сравниваются только по коду,
+/// а описание из каталога в сравнении не участвует.
+#[derive(Debug, Clone)]
 pub struct Episode {
     code: String,
     season: u8,
     episode: u8,
+    metadata: Option<Arc<EpisodeMetadata>>,
 }
 
-impl Episode {
-    pub fn from(code: &str) -> Self {
-        assert!(code.len() == 6);
-        let chars: Vec<char> = code.chars().collect();
-        assert!(chars[0] == 's');
-        assert!(chars[3] == 'e');
+/// Описание серии из каталога.
+#[derive(PartialEq, Debug, Default)]
+pub struct EpisodeMetadata {
+    /// Оригинальное название.
+    pub title: Option<String>,
+    /// Название в переводе.
+    pub localized_title: Option<String>,
+    pub air_date: Option<NaiveDate>,
+    /// Продолжительность в минутах.
+    pub runtime: Option<u16>,
+    /// Краткое описание сюжета.
+    pub synopsis: Option<String>,
+}
 
+impl Episode {
+    pub fn with_metadata(self, metadata: EpisodeMetadata) -> Self {
         Self {
-            code: code.to_string(),
-            season: code[1..=2].parse().expect("cant parse season"),
-            episode: code[4..=5].parse().expect("cant parse episode"),
+            metadata: Some(Arc::new(metadata)),
+            ..self
         }
     }
 
@@ -30,6 +49,60 @@ impl Episode {
     pub fn episode(&self) -> u8 {
         self.episode
     }
+
+    pub fn metadata(&self) -> Option<&EpisodeMetadata> {
+        self.metadata.as_deref()
+    }
+}
+
+/// Разбирает код серии вида `sNNeNN`.
+impl FromStr for Episode {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let parse_error =
+            || Error::EpisodeParseError(format!("код серии должен иметь вид sNNeNN: code={code}"));
+
+        let (season, episode) = code
+            .strip_prefix('s')
+            .and_then(|rest| rest.split_once('e'))
+            .ok_or_else(parse_error)?;
+
+        let is_two_digits =
+            |number: &str| number.len() == 2 && number.bytes().all(|b| b.is_ascii_digit());
+        if !is_two_digits(season) || !is_two_digits(episode) {
+            return Err(parse_error());
+        }
+
+        Ok(Self {
+            code: code.to_string(),
+            season: season.parse().map_err(|_| parse_error())?,
+            episode: episode.parse().map_err(|_| parse_error())?,
+            metadata: None,
+        })
+    }
+}
+
+impl TryFrom<&str> for Episode {
+    type Error = Error;
+
+    fn try_from(code: &str) -> Result<Self, Self::Error> {
+        code.parse()
+    }
+}
+
+impl PartialEq for Episode {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl Eq for Episode {}
+
+impl Hash for Episode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+    }
 }
 
 #[cfg(test)]
@@ -37,18 +110,55 @@ mod test {
     use super::*;
 
     #[test]
-    fn episode_from_fn_works_as_expected() {
+    fn episode_from_str_fn_works_as_expected() {
         let code = "s01e03".to_string();
 
-        let episode = Episode::from(&code);
+        let result = code.parse::<Episode>();
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let episode = result.unwrap();
+        assert_eq!(episode.code(), code);
+        assert_eq!(episode.season(), 1);
+        assert_eq!(episode.episode(), 3);
+        assert_eq!(episode.metadata(), None);
+    }
+
+    #[test]
+    fn episode_try_from_fn_works_as_expected() {
+        let result = Episode::try_from("s10e17");
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let episode = result.unwrap();
+        assert_eq!(episode.season(), 10);
+        assert_eq!(episode.episode(), 17);
+    }
+
+    #[test]
+    fn episode_from_str_fn_rejects_malformed_codes() {
+        for code in [
+            "", "s", "xyz", "s1e1", "x01e01", "s01x01", "s0ae01", "s01e0б", "s0бe0", "s01e01 ",
+            "s+1e01", "s01e001", "S01E01",
+        ] {
+            let result = code.parse::<Episode>();
+
+            assert!(
+                matches!(result, Err(Error::EpisodeParseError(_))),
+                "code {code:?} should be rejected, got {result:#?}"
+            );
+        }
+    }
+
+    #[test]
+    fn episode_eq_ignores_metadata() {
+        let episode = Episode::try_from("s01e03").unwrap();
+        let described = Episode::try_from("s01e03")
+            .unwrap()
+            .with_metadata(EpisodeMetadata {
+                title: Some(String::from("The One with the Thumb")),
+                ..Default::default()
+            });
 
-        assert_eq!(
-            episode,
-            Episode {
-                code,
-                season: 1,
-                episode: 3
-            }
-        );
+        assert_eq!(episode, described);
+        assert_ne!(episode, Episode::try_from("s01e04").unwrap());
     }
 }
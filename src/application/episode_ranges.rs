@@ -0,0 +1,189 @@
+use super::{Episode, Error, Show};
+use std::{collections::HashSet, str::FromStr};
+
+/// Серии, которые пользователь перечислил вручную: коды серий, диапазоны серий и целые
+/// сезоны через пробел или запятую, например `s01 s02e01-s02e10, s03e05`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpisodeRanges(Vec<EpisodeRange>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum EpisodeRange {
+    Episode(Episode),
+    /// Серии от первой до последней включительно, границы могут быть в разных сезонах.
+    Range(Episode, Episode),
+    Season(u8),
+}
+
+impl EpisodeRanges {
+    /// Серии сериала из набора в порядке каталога, без повторов. Каждая серия, диапазон
+    /// и сезон должны быть в каталоге.
+    pub fn resolve<'a>(&self, show: &'a Show) -> Result<Vec<&'a Episode>, Error> {
+        let mut selected = HashSet::new();
+        for range in &self.0 {
+            let episodes: Vec<&Episode> = match range {
+                EpisodeRange::Episode(episode) => vec![show.find(episode.code())?],
+                EpisodeRange::Range(first, last) => {
+                    show.find(first.code())?;
+                    show.find(last.code())?;
+
+                    let bounds =
+                        (first.season(), first.episode())..=(last.season(), last.episode());
+                    show.episodes()
+                        .iter()
+                        .filter(|episode| bounds.contains(&(episode.season(), episode.episode())))
+                        .collect()
+                }
+                EpisodeRange::Season(season) => {
+                    let episodes: Vec<&Episode> = show
+                        .episodes()
+                        .iter()
+                        .filter(|episode| episode.season() == *season)
+                        .collect();
+                    if episodes.is_empty() {
+                        return Err(Error::EpisodeRangeError(format!(
+                            "в сериале нет {season} сезона"
+                        )));
+                    }
+
+                    episodes
+                }
+            };
+            selected.extend(episodes);
+        }
+
+        Ok(show
+            .episodes()
+            .iter()
+            .filter(|episode| selected.contains(episode))
+            .collect())
+    }
+}
+
+/// Разбирает список вида `s01 s02e01-s02e10, s03e05`. Регистр букв не важен.
+impl FromStr for EpisodeRanges {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let ranges = value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|token| !token.is_empty())
+            .map(|token| token.to_lowercase().parse())
+            .collect::<Result<Vec<EpisodeRange>, Error>>()?;
+
+        if ranges.is_empty() {
+            return Err(Error::EpisodeRangeError(String::from(
+                "не указано ни одной серии",
+            )));
+        }
+
+        Ok(Self(ranges))
+    }
+}
+
+impl FromStr for EpisodeRange {
+    type Err = Error;
+
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let parse_error = || {
+            Error::EpisodeRangeError(format!(
+                "ожидали код серии sNNeNN, диапазон sNNeNN-sNNeNN или сезон sNN: {token}"
+            ))
+        };
+
+        if let Some((first, last)) = token.split_once('-') {
+            let first = Episode::try_from(first).map_err(|_| parse_error())?;
+            let last = Episode::try_from(last).map_err(|_| parse_error())?;
+            if (first.season(), first.episode()) > (last.season(), last.episode()) {
+                return Err(Error::EpisodeRangeError(format!(
+                    "начало диапазона позже его конца: {token}"
+                )));
+            }
+
+            return Ok(Self::Range(first, last));
+        }
+
+        if let Some(season) = token
+            .strip_prefix('s')
+            .filter(|season| !season.is_empty() && season.bytes().all(|b| b.is_ascii_digit()))
+        {
+            return match season.parse() {
+                Ok(season) if season > 0 => Ok(Self::Season(season)),
+                _ => Err(parse_error()),
+            };
+        }
+
+        Episode::try_from(token)
+            .map(Self::Episode)
+            .map_err(|_| parse_error())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application::Catalogue;
+
+    fn build_catalogue() -> Catalogue {
+        Catalogue::from_json(
+            r#"{"shows": [{"id": "friends", "name": "Друзья", "episodes": [
+                {"code": "s01e01"}, {"code": "s01e02"}, {"code": "s01e03"},
+                {"code": "s02e01"}, {"code": "s02e02"}, {"code": "s03e01"}
+            ]}]}"#,
+        )
+        .unwrap()
+    }
+
+    fn codes(episodes: Vec<&Episode>) -> Vec<&str> {
+        episodes.into_iter().map(Episode::code).collect()
+    }
+
+    #[test]
+    fn episode_ranges_resolve_fn_expands_codes_ranges_and_seasons() {
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+        let ranges: EpisodeRanges = "s03e01, S02 s01e02-s02e01 s01e03".parse().unwrap();
+
+        let result = ranges.resolve(show);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            codes(result.unwrap()),
+            vec!["s01e02", "s01e03", "s02e01", "s02e02", "s03e01"]
+        );
+    }
+
+    #[test]
+    fn episode_ranges_resolve_fn_rejects_episodes_missing_from_catalogue() {
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+
+        for value in ["s01e09", "s01e01-s01e09", "s05"] {
+            let ranges: EpisodeRanges = value.parse().unwrap();
+
+            let result = ranges.resolve(show);
+
+            assert!(result.is_err(), "value: {value}, result: {result:#?}");
+        }
+    }
+
+    #[test]
+    fn episode_ranges_from_str_fn_rejects_malformed_input() {
+        for value in [
+            "",
+            " , ",
+            "s01e02-s01e01",
+            "s00",
+            "s",
+            "s01e01-",
+            "e01",
+            "s01e1",
+        ] {
+            let result = value.parse::<EpisodeRanges>();
+
+            assert!(
+                matches!(result, Err(Error::EpisodeRangeError(_))),
+                "value: {value}, result: {result:#?}"
+            );
+        }
+    }
+}
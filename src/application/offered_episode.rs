@@ -0,0 +1,27 @@
+use super::Episode;
+use chrono::{DateTime, Utc};
+
+/// Отметка о том, что бот предложил пользователю серию. Вместе с отметками о просмотре
+/// показывает, какие предложения пользователи принимают.
+#[derive(PartialEq, Debug, Clone)]
+pub struct OfferedEpisode {
+    episode: Episode,
+    offered_at: DateTime<Utc>,
+}
+
+impl OfferedEpisode {
+    pub fn new(episode: Episode, offered_at: DateTime<Utc>) -> Self {
+        Self {
+            episode,
+            offered_at,
+        }
+    }
+
+    pub fn episode(&self) -> &Episode {
+        &self.episode
+    }
+
+    pub fn offered_at(&self) -> DateTime<Utc> {
+        self.offered_at
+    }
+}
@@ -0,0 +1,121 @@
+use super::Error;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// Ограничение случайного выбора серий одним сезоном или диапазоном сезонов.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeasonFilter {
+    first: u8,
+    last: u8,
+}
+
+impl SeasonFilter {
+    pub fn new(first: u8, last: u8) -> Result<Self, Error> {
+        if first == 0 || first > last {
+            return Err(Error::SeasonFilterError(format!(
+                "сезоны должны идти по возрастанию и начинаться с первого: first={first}, last={last}"
+            )));
+        }
+
+        Ok(Self { first, last })
+    }
+
+    pub fn single(season: u8) -> Result<Self, Error> {
+        Self::new(season, season)
+    }
+
+    pub fn first(&self) -> u8 {
+        self.first
+    }
+
+    pub fn last(&self) -> u8 {
+        self.last
+    }
+
+    pub fn contains(&self, season: u8) -> bool {
+        (self.first..=self.last).contains(&season)
+    }
+}
+
+/// Разбирает номер сезона `N` или диапазон `N-M`.
+impl FromStr for SeasonFilter {
+    type Err = Error;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let parse_error = || {
+            Error::SeasonFilterError(format!(
+                "ожидали номер сезона или диапазон вида 1-4: filter={filter}"
+            ))
+        };
+        let parse_season = |season: &str| season.trim().parse::<u8>().map_err(|_| parse_error());
+
+        // в телеграме тире часто подставляется вместо дефиса автоматически
+        match filter.trim().split_once(['-', '–', '—']) {
+            Some((first, last)) => Self::new(parse_season(first)?, parse_season(last)?),
+            None => Self::single(parse_season(filter)?),
+        }
+    }
+}
+
+impl Display for SeasonFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn season_filter_from_str_fn_parses_single_season_and_range() {
+        assert_eq!(
+            "3".parse::<SeasonFilter>().unwrap(),
+            SeasonFilter::single(3).unwrap()
+        );
+        assert_eq!(
+            "1-4".parse::<SeasonFilter>().unwrap(),
+            SeasonFilter::new(1, 4).unwrap()
+        );
+        assert_eq!(
+            " 2 – 5 ".parse::<SeasonFilter>().unwrap(),
+            SeasonFilter::new(2, 5).unwrap()
+        );
+    }
+
+    #[test]
+    fn season_filter_from_str_fn_rejects_malformed_filters() {
+        for filter in ["", "all", "0", "4-1", "1-", "-3", "1-2-3", "256", "1..4"] {
+            let result = filter.parse::<SeasonFilter>();
+
+            assert!(
+                matches!(result, Err(Error::SeasonFilterError(_))),
+                "filter {filter:?} is parsed: {result:#?}"
+            );
+        }
+    }
+
+    #[test]
+    fn season_filter_display_fn_round_trips() {
+        for filter in [
+            SeasonFilter::single(7).unwrap(),
+            SeasonFilter::new(1, 4).unwrap(),
+        ] {
+            assert_eq!(filter.to_string().parse::<SeasonFilter>().unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn season_filter_contains_fn_includes_bounds() {
+        let filter = SeasonFilter::new(2, 4).unwrap();
+
+        assert!(!filter.contains(1));
+        assert!(filter.contains(2));
+        assert!(filter.contains(4));
+        assert!(!filter.contains(5));
+    }
+}
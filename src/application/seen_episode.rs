@@ -0,0 +1,86 @@
+use super::Episode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Отметка о том, что пользователь посмотрел серию.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SeenEpisode {
+    episode: Episode,
+    seen_at: Option<DateTime<Utc>>,
+    source: SeenEpisodeSource,
+}
+
+impl SeenEpisode {
+    pub fn new(
+        episode: Episode,
+        seen_at: Option<DateTime<Utc>>,
+        source: SeenEpisodeSource,
+    ) -> Self {
+        Self {
+            episode,
+            seen_at,
+            source,
+        }
+    }
+
+    pub fn episode(&self) -> &Episode {
+        &self.episode
+    }
+
+    /// Время просмотра. Отсутствует у отметок, перенесённых из старого формата хранения.
+    pub fn seen_at(&self) -> Option<DateTime<Utc>> {
+        self.seen_at
+    }
+
+    pub fn source(&self) -> SeenEpisodeSource {
+        self.source
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeenEpisodeSource {
+    /// Отмечено кнопкой «Посмотрел» под предложенной серией.
+    Bot,
+    /// Перенесено из старого формата хранения, где время и источник не сохранялись.
+    Legacy,
+    /// Отмечено вручную командой `/mark`.
+    Manual,
+}
+
+impl SeenEpisodeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeenEpisodeSource::Bot => "bot",
+            SeenEpisodeSource::Legacy => "legacy",
+            SeenEpisodeSource::Manual => "manual",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bot" => Some(SeenEpisodeSource::Bot),
+            "legacy" => Some(SeenEpisodeSource::Legacy),
+            "manual" => Some(SeenEpisodeSource::Manual),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seen_episode_source_from_name_fn_accepts_as_str_output() {
+        for source in [
+            SeenEpisodeSource::Bot,
+            SeenEpisodeSource::Legacy,
+            SeenEpisodeSource::Manual,
+        ] {
+            assert_eq!(SeenEpisodeSource::from_name(source.as_str()), Some(source));
+        }
+
+        assert_eq!(SeenEpisodeSource::from_name("unknown"), None);
+    }
+}
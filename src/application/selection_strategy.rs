@@ -0,0 +1,279 @@
+use super::{Episode, SeenEpisode};
+use chrono::{DateTime, Utc};
+use rand::{
+    RngCore,
+    seq::{IndexedRandom, SliceRandom},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Способ выбрать следующую серию из тех, что подходят под фильтр пользователя.
+pub trait SelectionStrategy {
+    /// Выбрать серию из `candidates`. В `seen_episodes` лежат отметки текущего круга
+    /// просмотра в порядке просмотра. Возвращает `None`, если выбрать нечего.
+    fn select<'a>(
+        &self,
+        candidates: &[&'a Episode],
+        seen_episodes: &[SeenEpisode],
+        now: DateTime<Utc>,
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Episode>;
+}
+
+/// Стратегия выбора, которую пользователь указал в настройках.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategyKind {
+    #[default]
+    UniformUnseen,
+    LeastRecent,
+    Weighted,
+}
+
+impl SelectionStrategyKind {
+    pub fn strategy(self) -> &'static (dyn SelectionStrategy + Sync) {
+        match self {
+            Self::UniformUnseen => &UniformUnseen,
+            Self::LeastRecent => &LeastRecent,
+            Self::Weighted => &Weighted,
+        }
+    }
+}
+
+/// Случайная серия из непросмотренных. Когда просмотрено всё, выбирать нечего.
+pub struct UniformUnseen;
+
+impl SelectionStrategy for UniformUnseen {
+    fn select<'a>(
+        &self,
+        candidates: &[&'a Episode],
+        seen_episodes: &[SeenEpisode],
+        _now: DateTime<Utc>,
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Episode> {
+        let last_seen = last_seen_at(seen_episodes);
+
+        choose_unseen(candidates, &last_seen, rng)
+    }
+}
+
+/// Случайная серия из непросмотренных, а когда просмотрено всё, серия, которую
+/// смотрели раньше всех остальных.
+pub struct LeastRecent;
+
+impl SelectionStrategy for LeastRecent {
+    fn select<'a>(
+        &self,
+        candidates: &[&'a Episode],
+        seen_episodes: &[SeenEpisode],
+        _now: DateTime<Utc>,
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Episode> {
+        let last_seen = last_seen_at(seen_episodes);
+        if let Some(episode) = choose_unseen(candidates, &last_seen, rng) {
+            return Some(episode);
+        }
+
+        // перемешиваем, чтобы из серий, просмотренных одновременно, выбиралась случайная;
+        // у старых отметок без времени ключ `None`, и они считаются самыми давними
+        let mut candidates = candidates.to_vec();
+        candidates.shuffle(rng);
+        candidates
+            .into_iter()
+            .min_by_key(|episode| last_seen.get(episode).copied().flatten())
+    }
+}
+
+/// Случайная серия из всех, но чем давнее серию смотрели, тем вероятнее её выбор.
+/// Непросмотренные серии и серии без времени просмотра весят как просмотренные
+/// [`Weighted::MAX_WEIGHT_DAYS`] дней назад.
+pub struct Weighted;
+
+impl Weighted {
+    pub const MAX_WEIGHT_DAYS: i64 = 365;
+}
+
+impl SelectionStrategy for Weighted {
+    fn select<'a>(
+        &self,
+        candidates: &[&'a Episode],
+        seen_episodes: &[SeenEpisode],
+        now: DateTime<Utc>,
+        rng: &mut dyn RngCore,
+    ) -> Option<&'a Episode> {
+        let last_seen = last_seen_at(seen_episodes);
+
+        candidates
+            .choose_weighted(rng, |episode| match last_seen.get(episode) {
+                Some(Some(seen_at)) => (now - *seen_at).num_days().clamp(1, Self::MAX_WEIGHT_DAYS),
+                _ => Self::MAX_WEIGHT_DAYS,
+            })
+            .ok()
+            .copied()
+    }
+}
+
+/// Когда каждую серию смотрели последний раз. У старых отметок времени нет, для них `None`.
+fn last_seen_at(seen_episodes: &[SeenEpisode]) -> HashMap<&Episode, Option<DateTime<Utc>>> {
+    let mut last_seen = HashMap::new();
+    for seen_episode in seen_episodes {
+        let seen_at = last_seen
+            .entry(seen_episode.episode())
+            .or_insert(seen_episode.seen_at());
+        *seen_at = (*seen_at).max(seen_episode.seen_at());
+    }
+
+    last_seen
+}
+
+fn choose_unseen<'a>(
+    candidates: &[&'a Episode],
+    last_seen: &HashMap<&Episode, Option<DateTime<Utc>>>,
+    rng: &mut dyn RngCore,
+) -> Option<&'a Episode> {
+    let unseen: Vec<&Episode> = candidates
+        .iter()
+        .copied()
+        .filter(|episode| !last_seen.contains_key(episode))
+        .collect();
+
+    unseen.choose(rng).copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application::SeenEpisodeSource;
+    use chrono::TimeDelta;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn episodes(codes: &[&str]) -> Vec<Episode> {
+        codes
+            .iter()
+            .map(|&code| Episode::try_from(code).unwrap())
+            .collect()
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    fn seen_days_ago(code: &str, days: i64) -> SeenEpisode {
+        SeenEpisode::new(
+            Episode::try_from(code).unwrap(),
+            Some(now() - TimeDelta::days(days)),
+            SeenEpisodeSource::Bot,
+        )
+    }
+
+    fn legacy(code: &str) -> SeenEpisode {
+        SeenEpisode::new(
+            Episode::try_from(code).unwrap(),
+            None,
+            SeenEpisodeSource::Legacy,
+        )
+    }
+
+    #[test]
+    fn uniform_unseen_select_fn_returns_only_unseen_episodes() {
+        let episodes = episodes(&["s01e01", "s01e02", "s01e03"]);
+        let candidates: Vec<&Episode> = episodes.iter().collect();
+        let seen_episodes = vec![seen_days_ago("s01e01", 1), legacy("s01e03")];
+        let mut rng = StdRng::seed_from_u64(317);
+
+        for _ in 0..20 {
+            let result = UniformUnseen.select(&candidates, &seen_episodes, now(), &mut rng);
+
+            assert_eq!(result, Some(&episodes[1]));
+        }
+    }
+
+    #[test]
+    fn uniform_unseen_select_fn_returns_none_if_everything_is_seen() {
+        let episodes = episodes(&["s01e01"]);
+        let candidates: Vec<&Episode> = episodes.iter().collect();
+        let mut rng = StdRng::seed_from_u64(317);
+
+        let result =
+            UniformUnseen.select(&candidates, &[seen_days_ago("s01e01", 1)], now(), &mut rng);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn least_recent_select_fn_prefers_unseen_episodes() {
+        let episodes = episodes(&["s01e01", "s01e02"]);
+        let candidates: Vec<&Episode> = episodes.iter().collect();
+        let mut rng = StdRng::seed_from_u64(317);
+
+        let result = LeastRecent.select(
+            &candidates,
+            &[seen_days_ago("s01e01", 300)],
+            now(),
+            &mut rng,
+        );
+
+        assert_eq!(result, Some(&episodes[1]));
+    }
+
+    #[test]
+    fn least_recent_select_fn_returns_episode_seen_longest_ago() {
+        let episodes = episodes(&["s01e01", "s01e02", "s01e03"]);
+        let candidates: Vec<&Episode> = episodes.iter().collect();
+        let seen_episodes = vec![
+            seen_days_ago("s01e01", 30),
+            seen_days_ago("s01e02", 10),
+            seen_days_ago("s01e03", 20),
+            // пересмотр обновляет время последнего просмотра
+            seen_days_ago("s01e01", 5),
+        ];
+        let mut rng = StdRng::seed_from_u64(317);
+
+        let result = LeastRecent.select(&candidates, &seen_episodes, now(), &mut rng);
+
+        assert_eq!(result, Some(&episodes[2]));
+    }
+
+    #[test]
+    fn least_recent_select_fn_treats_legacy_marks_as_oldest() {
+        let episodes = episodes(&["s01e01", "s01e02"]);
+        let candidates: Vec<&Episode> = episodes.iter().collect();
+        let seen_episodes = vec![seen_days_ago("s01e01", 300), legacy("s01e02")];
+        let mut rng = StdRng::seed_from_u64(317);
+
+        let result = LeastRecent.select(&candidates, &seen_episodes, now(), &mut rng);
+
+        assert_eq!(result, Some(&episodes[1]));
+    }
+
+    #[test]
+    fn weighted_select_fn_prefers_episodes_seen_long_ago() {
+        let episodes = episodes(&["s01e01", "s01e02"]);
+        let candidates: Vec<&Episode> = episodes.iter().collect();
+        let seen_episodes = vec![seen_days_ago("s01e01", 1), seen_days_ago("s01e02", 200)];
+        let mut rng = StdRng::seed_from_u64(317);
+
+        let picks_of_old_episode = (0..1000)
+            .filter_map(|_| Weighted.select(&candidates, &seen_episodes, now(), &mut rng))
+            .filter(|&episode| episode == &episodes[1])
+            .count();
+
+        // ожидаемая доля 200 / 201, оставляем запас на случайность
+        assert!(picks_of_old_episode > 950, "picks: {picks_of_old_episode}");
+    }
+
+    #[test]
+    fn every_strategy_returns_none_without_candidates() {
+        let mut rng = StdRng::seed_from_u64(317);
+
+        for kind in [
+            SelectionStrategyKind::UniformUnseen,
+            SelectionStrategyKind::LeastRecent,
+            SelectionStrategyKind::Weighted,
+        ] {
+            let result = kind.strategy().select(&[], &[], now(), &mut rng);
+
+            assert_eq!(result, None, "strategy: {kind:?}");
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use super::{SeasonFilter, SelectionStrategyKind, ShowID};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Настройки пользователя, которые хранятся рядом с его историей просмотров.
+///
+/// Отсутствующие в сохранённых настройках поля берутся из настроек по умолчанию,
+/// поэтому новые поля не ломают чтение уже сохранённых настроек.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// Выбранный сериал. Если не выбран, используется первый сериал из каталога.
+    pub show: Option<ShowID>,
+    /// Сезоны, из которых выбирается следующая серия, для каждого сериала отдельно.
+    pub season_filters: BTreeMap<ShowID, SeasonFilter>,
+    /// Язык названий серий.
+    pub language: Language,
+    /// Источник ссылок для просмотра. Если не выбран, используется источник по умолчанию.
+    pub watch_provider: Option<String>,
+    /// Бесконечный просмотр: когда просмотрены все серии сериала, завершённый круг
+    /// уходит в архив и начинается новый.
+    pub auto_reset: bool,
+    /// Как выбирать следующую серию.
+    pub selection_strategy: SelectionStrategyKind,
+    /// Показывать ли клавиатуру с кнопкой «Ещё серию».
+    pub show_keyboard: bool,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            show: None,
+            season_filters: BTreeMap::new(),
+            language: Language::default(),
+            watch_provider: None,
+            auto_reset: false,
+            selection_strategy: SelectionStrategyKind::default(),
+            show_keyboard: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    /// Название в переводе, а рядом оригинальное.
+    #[default]
+    #[serde(rename = "ru")]
+    Russian,
+    /// Только оригинальное название.
+    #[serde(rename = "en")]
+    English,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn user_settings_deserialization_fills_missing_fields_with_defaults() {
+        let result = serde_json::from_str::<UserSettings>(r#"{"show":"the-office"}"#);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap(),
+            UserSettings {
+                show: Some(ShowID::new("the-office")),
+                ..UserSettings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn user_settings_serialization_round_trips() {
+        let settings = UserSettings {
+            show: Some(ShowID::new("the-office")),
+            season_filters: [(ShowID::default(), SeasonFilter::new(1, 4).unwrap())].into(),
+            language: Language::English,
+            watch_provider: Some(String::from("kinopoisk")),
+            auto_reset: true,
+            selection_strategy: SelectionStrategyKind::Weighted,
+            show_keyboard: false,
+        };
+
+        let json = serde_json::to_string(&settings).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<UserSettings>(&json).unwrap(),
+            settings
+        );
+    }
+}
@@ -0,0 +1,157 @@
+pub mod file;
+pub mod memory;
+pub mod sqlite;
+#[cfg(test)]
+mod test_suite;
+
+use super::{Episode, Error, OfferedEpisode, SeenEpisode, ShowID, UserID, UserSettings};
+use chrono::Utc;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Сколько последних предложенных серий хранилище держит для каждого пользователя и сериала.
+/// Более старые предложения удаляются при добавлении новых.
+pub const OFFERS_RETENTION: usize = 100;
+
+/// Хранилище отметок о просмотренных пользователями сериях и настроек пользователей.
+///
+/// История просмотров у каждого сериала своя. Отметки возвращаются в порядке просмотра:
+/// в начале старые, в конце недавние.
+pub trait SeenEpisodesStore {
+    /// Загрузить отметки о просмотренных пользователем сериях сериала.
+    fn load(&self, user_id: &UserID, show_id: &ShowID) -> Result<Vec<SeenEpisode>, Error>;
+
+    /// Добавить отметку в конец списка просмотренных пользователем серий сериала.
+    fn append(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        seen_episode: SeenEpisode,
+    ) -> Result<(), Error>;
+
+    /// Добавить несколько отметок в конец списка одной записью: либо сохраняются все,
+    /// либо ни одной.
+    fn append_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        seen_episodes: Vec<SeenEpisode>,
+    ) -> Result<(), Error>;
+
+    /// Удалить одной записью все отметки о просмотре серий `episodes` в текущем круге.
+    fn remove_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episodes: &[Episode],
+    ) -> Result<(), Error>;
+
+    /// Удалить последнюю отметку о просмотре серии `episode` или, если серия не указана,
+    /// последнюю отметку вообще. Затрагивает только текущий круг просмотра.
+    ///
+    /// Возвращает удалённую отметку или `None`, если удалять было нечего.
+    fn remove_last(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episode: Option<&Episode>,
+    ) -> Result<Option<SeenEpisode>, Error>;
+
+    /// Перечислить пользователей, у которых есть просмотренные серии хотя бы одного сериала.
+    fn list(&self) -> Result<Vec<UserID>, Error>;
+
+    /// Удалить все просмотренные пользователем серии сериала. Архив не затрагивается.
+    fn clear(&self, user_id: &UserID, show_id: &ShowID) -> Result<(), Error>;
+
+    /// Перенести просмотренные серии сериала в архив завершённым кругом просмотра.
+    /// После этого [`load`](Self::load) возвращает пустой список.
+    ///
+    /// Возвращает число завершённых кругов. Пустой список в архив не переносится.
+    fn archive(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error>;
+
+    /// То же, что [`archive`](Self::archive), но только если в текущем круге отмечены
+    /// все серии `episodes`. Иначе ничего не меняет и возвращает `None`.
+    ///
+    /// Реализация по умолчанию проверяет и переносит двумя шагами и полагается на то,
+    /// что историю пользователя в это время никто не меняет.
+    fn archive_completed(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episodes: &[Episode],
+    ) -> Result<Option<u32>, Error> {
+        let seen_episodes = self.load(user_id, show_id)?;
+        let seen_set: HashSet<&Episode> = seen_episodes.iter().map(SeenEpisode::episode).collect();
+        if !episodes.iter().all(|episode| seen_set.contains(episode)) {
+            return Ok(None);
+        }
+
+        self.archive(user_id, show_id).map(Some)
+    }
+
+    /// Сколько кругов просмотра сериала пользователь завершил.
+    fn completed_cycles(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error>;
+
+    /// Добавить отметку о предложенной пользователю серии сериала. Из отметок этого
+    /// пользователя и сериала остаются только [`OFFERS_RETENTION`] последних.
+    fn append_offer(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        offered_episode: OfferedEpisode,
+    ) -> Result<(), Error>;
+
+    /// Загрузить не больше `limit` последних предложенных пользователю серий сериала
+    /// в порядке предложения. Больше [`OFFERS_RETENTION`] серий не вернётся.
+    fn load_recent_offers(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        limit: usize,
+    ) -> Result<Vec<OfferedEpisode>, Error>;
+
+    /// Загрузить настройки пользователя. Если их ещё нет, возвращаются настройки по умолчанию.
+    fn load_settings(&self, user_id: &UserID) -> Result<UserSettings, Error>;
+
+    /// Сохранить настройки пользователя.
+    fn save_settings(&self, user_id: &UserID, settings: &UserSettings) -> Result<(), Error>;
+
+    /// Привести данные хранилища к актуальной версии схемы.
+    ///
+    /// Вызывается один раз при запуске, до первого обращения к данным.
+    fn migrate(&self) -> Result<MigrationReport, Error>;
+}
+
+/// Итог миграции хранилища.
+#[derive(Debug, PartialEq)]
+pub struct MigrationReport {
+    /// Версия схемы, найденная в хранилище при запуске.
+    pub from_version: u32,
+    /// Версия схемы после миграции.
+    pub to_version: u32,
+    /// Сколько пользователей затронула миграция.
+    pub migrated_users: usize,
+    /// Где лежит резервная копия данных до миграции, если она понадобилась.
+    pub backup_path: Option<PathBuf>,
+}
+
+impl MigrationReport {
+    fn up_to_date(version: u32) -> Self {
+        Self {
+            from_version: version,
+            to_version: version,
+            migrated_users: 0,
+            backup_path: None,
+        }
+    }
+}
+
+/// Папка для резервной копии данных перед миграцией со схемы версии `from_version`.
+fn build_backup_path(storage_path: &Path, from_version: u32) -> PathBuf {
+    storage_path.join("backups").join(format!(
+        "{}-v{from_version}",
+        Utc::now().format("%Y%m%dT%H%M%S")
+    ))
+}
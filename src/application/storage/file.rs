@@ -0,0 +1,1033 @@
+mod migration;
+
+use super::{
+    Error, MigrationReport, OFFERS_RETENTION, OfferedEpisode, SeenEpisode, SeenEpisodesStore,
+    ShowID, UserID, UserSettings,
+};
+use crate::application::{Episode, SeenEpisodeSource};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+pub fn new(storage_path: PathBuf) -> Store {
+    Store { storage_path }
+}
+
+/// Хранит просмотренные серии в отдельном файле `shows/{show_id}/{user_id}.txt` для каждого
+/// пользователя и сериала, а настройки пользователя в файле `settings/{user_id}.json`.
+/// Завершённые круги просмотра лежат в `shows/{show_id}/archive/{user_id}/{cycle}.txt`,
+/// а предложенные ботом серии в журнале `shows/{show_id}/offers/{user_id}.txt`. В журнале
+/// предложений остаются только [`OFFERS_RETENTION`] последних строк, поэтому он читается
+/// целиком.
+///
+/// Файл является журналом: каждая строка это JSON с одной отметкой о просмотре, новые
+/// отметки дописываются в конец. Файлы старого формата, где в каждой строке был только
+/// код серии, а недавние серии были наверху, тоже читаются и переводятся в новый формат
+/// при первой же записи или при миграции хранилища.
+pub struct Store {
+    storage_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    code: String,
+    seen_at: Option<DateTime<Utc>>,
+    source: SeenEpisodeSource,
+}
+
+impl From<&SeenEpisode> for JournalEntry {
+    fn from(seen_episode: &SeenEpisode) -> Self {
+        Self {
+            code: seen_episode.episode().code().to_string(),
+            seen_at: seen_episode.seen_at(),
+            source: seen_episode.source(),
+        }
+    }
+}
+
+impl TryFrom<JournalEntry> for SeenEpisode {
+    type Error = Error;
+
+    fn try_from(entry: JournalEntry) -> Result<Self, Self::Error> {
+        let episode = Episode::try_from(entry.code.as_str())?;
+
+        Ok(SeenEpisode::new(episode, entry.seen_at, entry.source))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OfferEntry {
+    code: String,
+    offered_at: DateTime<Utc>,
+}
+
+impl From<&OfferedEpisode> for OfferEntry {
+    fn from(offered_episode: &OfferedEpisode) -> Self {
+        Self {
+            code: offered_episode.episode().code().to_string(),
+            offered_at: offered_episode.offered_at(),
+        }
+    }
+}
+
+impl TryFrom<OfferEntry> for OfferedEpisode {
+    type Error = Error;
+
+    fn try_from(entry: OfferEntry) -> Result<Self, Self::Error> {
+        let episode = Episode::try_from(entry.code.as_str())?;
+
+        Ok(OfferedEpisode::new(episode, entry.offered_at))
+    }
+}
+
+impl SeenEpisodesStore for Store {
+    fn load(&self, user_id: &UserID, show_id: &ShowID) -> Result<Vec<SeenEpisode>, Error> {
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+
+        Ok(self.read_db_from_file(&user_storage_path)?)
+    }
+
+    fn append(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        seen_episode: SeenEpisode,
+    ) -> Result<(), Error> {
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+
+        if self.is_legacy_file(&user_storage_path)? {
+            let mut seen_episodes = self.read_db_from_file(&user_storage_path)?;
+            seen_episodes.push(seen_episode);
+
+            self.save_db_to_file(seen_episodes, &user_storage_path)?;
+
+            return Ok(());
+        }
+
+        self.append_to_file(&[seen_episode], &user_storage_path)?;
+
+        Ok(())
+    }
+
+    fn append_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        mut seen_episodes: Vec<SeenEpisode>,
+    ) -> Result<(), Error> {
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+
+        if self.is_legacy_file(&user_storage_path)? {
+            let mut all_seen_episodes = self.read_db_from_file(&user_storage_path)?;
+            all_seen_episodes.append(&mut seen_episodes);
+
+            self.save_db_to_file(all_seen_episodes, &user_storage_path)?;
+
+            return Ok(());
+        }
+
+        self.append_to_file(&seen_episodes, &user_storage_path)?;
+
+        Ok(())
+    }
+
+    fn remove_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episodes: &[Episode],
+    ) -> Result<(), Error> {
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+
+        let mut seen_episodes = self.read_db_from_file(&user_storage_path)?;
+        let count = seen_episodes.len();
+        seen_episodes.retain(|seen_episode| !episodes.contains(seen_episode.episode()));
+
+        if seen_episodes.is_empty() {
+            self.clear(user_id, show_id)?;
+        } else if seen_episodes.len() != count {
+            self.save_db_to_file(seen_episodes, &user_storage_path)?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_last(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episode: Option<&Episode>,
+    ) -> Result<Option<SeenEpisode>, Error> {
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+
+        let mut seen_episodes = self.read_db_from_file(&user_storage_path)?;
+        let Some(index) = seen_episodes.iter().rposition(|seen_episode| {
+            episode.is_none_or(|episode| seen_episode.episode() == episode)
+        }) else {
+            return Ok(None);
+        };
+        let removed = seen_episodes.remove(index);
+
+        if seen_episodes.is_empty() {
+            self.clear(user_id, show_id)?;
+        } else {
+            self.save_db_to_file(seen_episodes, &user_storage_path)?;
+        }
+
+        Ok(Some(removed))
+    }
+
+    fn list(&self) -> Result<Vec<UserID>, Error> {
+        let shows_path = self.storage_path.join("shows");
+        let entries = match fs::read_dir(&shows_path) {
+            Ok(entries) => entries,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                _ => return Err(Error::FileError(err)),
+            },
+        };
+
+        let mut user_ids = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                user_ids.append(&mut self.list_users_in(&path)?);
+            }
+        }
+        user_ids.sort();
+        user_ids.dedup();
+
+        Ok(user_ids)
+    }
+
+    fn clear(&self, user_id: &UserID, show_id: &ShowID) -> Result<(), Error> {
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+
+        fs::remove_file(user_storage_path).or_else(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(Error::FileError(err)),
+        })
+    }
+
+    fn archive(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error> {
+        let completed_cycles = self.completed_cycles(user_id, show_id)?;
+
+        let user_storage_path = self.build_user_storage_path(user_id, show_id);
+        if !user_storage_path.is_file() {
+            return Ok(completed_cycles);
+        }
+
+        let archive_path = self.build_archive_path(user_id, show_id);
+        self.create_directory_if_not_exists(&archive_path)?;
+        fs::rename(
+            &user_storage_path,
+            archive_path.join(format!("{}.txt", completed_cycles + 1)),
+        )?;
+
+        Ok(completed_cycles + 1)
+    }
+
+    fn completed_cycles(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error> {
+        let entries = match fs::read_dir(self.build_archive_path(user_id, show_id)) {
+            Ok(entries) => entries,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(0),
+                _ => return Err(Error::FileError(err)),
+            },
+        };
+
+        let mut completed_cycles = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "txt") {
+                completed_cycles += 1;
+            }
+        }
+
+        Ok(completed_cycles)
+    }
+
+    fn append_offer(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        offered_episode: OfferedEpisode,
+    ) -> Result<(), Error> {
+        let offers_path = self.build_offers_path(user_id, show_id);
+        if let Some(parent) = offers_path.parent() {
+            self.create_directory_if_not_exists(parent)?;
+        }
+
+        let mut line = serde_json::to_string(&OfferEntry::from(&offered_episode))
+            .map_err(std::io::Error::from)?;
+        line.push('\n');
+
+        let content = match fs::read_to_string(&offers_path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(Error::FileError(err)),
+        };
+        let lines: Vec<&str> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        if lines.len() < OFFERS_RETENTION {
+            let mut file = self.open_journal_for_append(&offers_path)?;
+            file.write_all(line.as_bytes())?;
+
+            return Ok(());
+        }
+
+        // журнал заполнен: переписываем его без самых старых строк
+        let mut trimmed = String::new();
+        for kept_line in &lines[lines.len() + 1 - OFFERS_RETENTION..] {
+            trimmed.push_str(kept_line);
+            trimmed.push('\n');
+        }
+        trimmed.push_str(&line);
+        self.write_file_atomically(&offers_path, trimmed.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn load_recent_offers(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        limit: usize,
+    ) -> Result<Vec<OfferedEpisode>, Error> {
+        let offers_path = self.build_offers_path(user_id, show_id);
+        let content = match fs::read_to_string(&offers_path) {
+            Ok(content) => content,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                _ => return Err(Error::FileError(err)),
+            },
+        };
+
+        let mut offers = Vec::new();
+        for line in content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            let offered_episode = serde_json::from_str::<OfferEntry>(line)
+                .map_err(|err| Error::EpisodeParseError(err.to_string()))
+                .and_then(OfferedEpisode::try_from);
+
+            match offered_episode {
+                Ok(offered_episode) => offers.push(offered_episode),
+                // журнал предложений нужен только для подсказок, его порча не критична
+                Err(err) => tracing::warn!(
+                    path = offers_path.to_string_lossy().to_string(),
+                    line = line,
+                    error = err.to_string(),
+                    "пропускаем повреждённую строку в журнале предложенных серий"
+                ),
+            }
+        }
+
+        let recent_offers = offers.split_off(offers.len().saturating_sub(limit));
+
+        Ok(recent_offers)
+    }
+
+    fn load_settings(&self, user_id: &UserID) -> Result<UserSettings, Error> {
+        let settings_path = self.build_settings_path(user_id);
+
+        let content = match fs::read_to_string(&settings_path) {
+            Ok(content) => content,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(UserSettings::default()),
+                _ => return Err(Error::FileError(err)),
+            },
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(settings) => Ok(settings),
+            // испорченные настройки не должны мешать пользоваться ботом
+            Err(err) => {
+                tracing::warn!(
+                    path = settings_path.to_string_lossy().to_string(),
+                    error = err.to_string(),
+                    "не удалось прочитать настройки пользователя, используем настройки по умолчанию"
+                );
+
+                Ok(UserSettings::default())
+            }
+        }
+    }
+
+    fn save_settings(&self, user_id: &UserID, settings: &UserSettings) -> Result<(), Error> {
+        let settings_path = self.build_settings_path(user_id);
+        if let Some(parent) = settings_path.parent() {
+            self.create_directory_if_not_exists(parent)?;
+        }
+
+        let content = serde_json::to_string(settings).map_err(std::io::Error::from)?;
+        self.write_file_atomically(&settings_path, content.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<MigrationReport, Error> {
+        migration::migrate(self)
+    }
+}
+
+impl Store {
+    fn build_user_storage_path(&self, user_id: &UserID, show_id: &ShowID) -> PathBuf {
+        self.storage_path
+            .join("shows")
+            .join(show_id.as_str())
+            .join(format!("{user_id}.txt"))
+    }
+
+    fn build_archive_path(&self, user_id: &UserID, show_id: &ShowID) -> PathBuf {
+        self.storage_path
+            .join("shows")
+            .join(show_id.as_str())
+            .join("archive")
+            .join(user_id.to_string())
+    }
+
+    fn build_offers_path(&self, user_id: &UserID, show_id: &ShowID) -> PathBuf {
+        self.storage_path
+            .join("shows")
+            .join(show_id.as_str())
+            .join("offers")
+            .join(format!("{user_id}.txt"))
+    }
+
+    fn build_settings_path(&self, user_id: &UserID) -> PathBuf {
+        self.storage_path
+            .join("settings")
+            .join(format!("{user_id}.json"))
+    }
+
+    /// Перечисляет пользователей по файлам `{user_id}.txt` в папке `path`.
+    fn list_users_in(&self, path: &Path) -> Result<Vec<UserID>, Error> {
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                _ => return Err(Error::FileError(err)),
+            },
+        };
+
+        let mut user_ids = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "txt") {
+                continue;
+            }
+
+            let Some(user_id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            else {
+                continue;
+            };
+
+            user_ids.push(UserID::new(user_id));
+        }
+        user_ids.sort();
+
+        Ok(user_ids)
+    }
+
+    fn read_db_from_file(&self, path: &Path) -> Result<Vec<SeenEpisode>, std::io::Error> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                _ => return Err(err),
+            },
+        };
+
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+
+        let mut legacy_episodes = Vec::new();
+        let mut seen_episodes = Vec::new();
+        for line in content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            let (seen_episode, episodes) = if line.starts_with('{') {
+                let seen_episode = serde_json::from_str::<JournalEntry>(line)
+                    .map_err(|err| Error::EpisodeParseError(err.to_string()))
+                    .and_then(SeenEpisode::try_from);
+
+                (seen_episode, &mut seen_episodes)
+            } else {
+                let seen_episode = Episode::try_from(line)
+                    .map(|episode| SeenEpisode::new(episode, None, SeenEpisodeSource::Legacy));
+
+                (seen_episode, &mut legacy_episodes)
+            };
+
+            match seen_episode {
+                Ok(seen_episode) => episodes.push(seen_episode),
+                // одна испорченная строка не должна лишать пользователя всей истории
+                Err(err) => tracing::warn!(
+                    path = path.to_string_lossy().to_string(),
+                    line = line,
+                    error = err.to_string(),
+                    "пропускаем повреждённую строку в файле просмотренных серий"
+                ),
+            }
+        }
+
+        // в старом формате недавние серии были наверху файла
+        legacy_episodes.reverse();
+        legacy_episodes.append(&mut seen_episodes);
+
+        Ok(legacy_episodes)
+    }
+
+    /// Проверяет, записан ли файл в старом формате, по его первой непустой строке.
+    fn is_legacy_file(&self, path: &Path) -> Result<bool, std::io::Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => match err.kind() {
+                std::io::ErrorKind::NotFound => return Ok(false),
+                _ => return Err(err),
+            },
+        };
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() {
+                return Ok(!line.starts_with('{'));
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Дописывает отметки в конец журнала за одну запись.
+    fn append_to_file(
+        &self,
+        seen_episodes: &[SeenEpisode],
+        path: &Path,
+    ) -> Result<(), std::io::Error> {
+        if seen_episodes.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            self.create_directory_if_not_exists(parent)?;
+        }
+
+        let mut lines = String::new();
+        for seen_episode in seen_episodes {
+            lines.push_str(&serde_json::to_string(&JournalEntry::from(seen_episode))?);
+            lines.push('\n');
+        }
+
+        let mut file = self.open_journal_for_append(path)?;
+        file.write_all(lines.as_bytes())?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Открывает журнал для дописывания. Если запись в журнал оборвалась при сбое, последняя
+    /// строка осталась без перевода строки: новая запись начнётся со следующей строки,
+    /// а не склеится с оборванной.
+    fn open_journal_for_append(&self, path: &Path) -> Result<File, std::io::Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+
+        if file.metadata()?.len() > 0 {
+            let mut last_byte = [0; 1];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last_byte)?;
+            if last_byte != *b"\n" {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        Ok(file)
+    }
+
+    fn save_db_to_file(
+        &self,
+        seen_episodes: Vec<SeenEpisode>,
+        path: &Path,
+    ) -> Result<(), std::io::Error> {
+        if seen_episodes.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            self.create_directory_if_not_exists(parent)?;
+        }
+
+        let mut content = String::new();
+        for seen_episode in &seen_episodes {
+            content.push_str(&serde_json::to_string(&JournalEntry::from(seen_episode))?);
+            content.push('\n');
+        }
+
+        self.write_file_atomically(path, content.as_bytes())
+    }
+
+    /// Записывает данные во временный файл рядом с `path` и затем переименовывает его в `path`.
+    /// Если что-то пойдёт не так, в `path` останется прежнее содержимое.
+    fn write_file_atomically(&self, path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+        let tmp_path = self.build_tmp_path(path);
+
+        let result = File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(content)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&tmp_path, path));
+
+        if let Err(err) = result {
+            if tmp_path.is_file() {
+                let _ = fs::remove_file(&tmp_path);
+            }
+            return Err(err);
+        }
+
+        // чтобы само переименование пережило падение системы, синхронизируем и папку
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            File::open(parent)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    fn build_tmp_path(&self, path: &Path) -> PathBuf {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+
+        path.with_file_name(file_name)
+    }
+
+    fn create_directory_if_not_exists(&self, path: &Path) -> Result<(), std::io::Error> {
+        fs::create_dir_all(path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::{NamedTempFile, TempDir};
+
+    use super::*;
+    use crate::application::storage::test_suite::seen_episodes_store_test_suite;
+
+    fn build_store() -> Store {
+        Store {
+            storage_path: "seen_episodes".into(),
+        }
+    }
+
+    fn seen(code: &str) -> SeenEpisode {
+        SeenEpisode::new(
+            Episode::try_from(code).unwrap(),
+            DateTime::from_timestamp(1_700_000_000, 0),
+            SeenEpisodeSource::Bot,
+        )
+    }
+
+    fn legacy(code: &str) -> SeenEpisode {
+        SeenEpisode::new(
+            Episode::try_from(code).unwrap(),
+            None,
+            SeenEpisodeSource::Legacy,
+        )
+    }
+
+    fn build_store_in_temp_dir() -> (Store, TempDir) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        (new(temp_dir.path().to_path_buf()), temp_dir)
+    }
+
+    seen_episodes_store_test_suite!(build_store_in_temp_dir);
+
+    #[test]
+    fn store_build_user_storage_path_fn_works_as_expected() {
+        let s = build_store();
+
+        let user_id = UserID::new(317);
+
+        let result = s.build_user_storage_path(&user_id, &ShowID::default());
+
+        assert_eq!(result, PathBuf::from("seen_episodes/shows/friends/317.txt"));
+    }
+
+    #[test]
+    fn store_read_db_from_file_fn_works_with_non_existing_file() {
+        let s = build_store();
+
+        let result = s.read_db_from_file(Path::new("non_existing_file.txt"));
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Vec::<SeenEpisode>::new());
+    }
+
+    #[test]
+    fn store_read_db_from_file_fn_handles_empty_file() {
+        let s = build_store();
+        let tmpfile = NamedTempFile::new().unwrap();
+
+        let result = s.read_db_from_file(tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Vec::<SeenEpisode>::new());
+    }
+
+    #[test]
+    fn store_read_db_from_file_fn_reads_data_from_legacy_file() {
+        let s = build_store();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        writeln!(tmpfile, "s01e02\ns01e01\n").unwrap();
+
+        let result = s.read_db_from_file(tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), vec!(legacy("s01e01"), legacy("s01e02"),));
+    }
+
+    #[test]
+    fn store_read_db_from_file_fn_reads_data_from_journal_file() {
+        let s = build_store();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        writeln!(
+            tmpfile,
+            r#"{{"code":"s01e01","seen_at":"2023-11-14T22:13:20Z","source":"bot"}}"#
+        )
+        .unwrap();
+        writeln!(
+            tmpfile,
+            r#"{{"code":"s01e02","seen_at":null,"source":"legacy"}}"#
+        )
+        .unwrap();
+
+        let result = s.read_db_from_file(tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), vec!(seen("s01e01"), legacy("s01e02")));
+    }
+
+    #[test]
+    fn store_read_db_from_file_fn_puts_legacy_lines_before_journal_entries() {
+        let s = build_store();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        writeln!(tmpfile, "s01e02\ns01e01").unwrap();
+        writeln!(
+            tmpfile,
+            r#"{{"code":"s01e03","seen_at":"2023-11-14T22:13:20Z","source":"bot"}}"#
+        )
+        .unwrap();
+
+        let result = s.read_db_from_file(tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap(),
+            vec!(legacy("s01e01"), legacy("s01e02"), seen("s01e03"))
+        );
+    }
+
+    #[test]
+    fn store_read_db_from_file_fn_skips_corrupted_lines() {
+        let s = build_store();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        writeln!(tmpfile, "s01e02\nxyz\ns01e01").unwrap();
+        writeln!(
+            tmpfile,
+            r#"{{"code":"s01e03","seen_at":"2023-11-14T22:13:20Z","source":"bot"}}"#
+        )
+        .unwrap();
+        writeln!(
+            tmpfile,
+            r#"{{"code":"s01x04","seen_at":"2023-11-14T22:13:20Z","source":"bot"}}"#
+        )
+        .unwrap();
+        // строка, оборванная при сбое во время записи
+        write!(tmpfile, r#"{{"code":"s01e05","seen_at":"2023-11"#).unwrap();
+
+        let result = s.read_db_from_file(tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap(),
+            vec!(legacy("s01e01"), legacy("s01e02"), seen("s01e03"))
+        );
+    }
+
+    #[test]
+    fn store_save_db_to_file_fn_saves_empty_list_to_file() {
+        let s = build_store();
+
+        let mut tmpfile = NamedTempFile::new().unwrap();
+
+        let result = s.save_db_to_file(Vec::new(), tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        let mut tmpfile_content = String::new();
+        tmpfile.read_to_string(&mut tmpfile_content).unwrap();
+        assert_eq!(tmpfile_content, "");
+    }
+
+    #[test]
+    fn store_save_db_to_file_fn_saves_non_empty_list_to_file_as_journal() {
+        let s = build_store();
+
+        let tmpfile = NamedTempFile::new().unwrap();
+
+        let seen_episodes = vec![seen("s01e01"), legacy("s01e02")];
+
+        let result = s.save_db_to_file(seen_episodes, tmpfile.path());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        // файл заменяется целиком, поэтому читаем его заново по пути
+        let tmpfile_content = fs::read_to_string(tmpfile.path()).unwrap();
+        assert_eq!(
+            tmpfile_content,
+            concat!(
+                r#"{"code":"s01e01","seen_at":"2023-11-14T22:13:20Z","source":"bot"}"#,
+                "\n",
+                r#"{"code":"s01e02","seen_at":null,"source":"legacy"}"#,
+                "\n",
+            )
+        );
+    }
+
+    #[test]
+    fn store_save_db_to_file_fn_does_not_leave_temporary_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let path = temp_dir.path().join("317.txt");
+
+        let result = s.save_db_to_file(vec![seen("s01e01")], &path);
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        assert!(!s.build_tmp_path(&path).exists());
+        assert_eq!(s.read_db_from_file(&path).unwrap(), vec![seen("s01e01")]);
+    }
+
+    #[test]
+    fn store_save_db_to_file_fn_keeps_previous_content_if_write_fails() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let path = temp_dir.path().join("317.txt");
+        fs::write(&path, "s01e02\ns01e01\n").unwrap();
+
+        // папка на месте временного файла не даст его создать
+        fs::create_dir(s.build_tmp_path(&path)).unwrap();
+
+        let result = s.save_db_to_file(vec![seen("s01e03")], &path);
+        assert!(result.is_err(), "result is not error: {result:#?}");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "s01e02\ns01e01\n");
+    }
+
+    #[test]
+    fn store_append_fn_keeps_legacy_history_if_conversion_fails() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let user_id = UserID::new(317);
+
+        let user_storage_path = s.build_user_storage_path(&user_id, &ShowID::default());
+        fs::create_dir_all(user_storage_path.parent().unwrap()).unwrap();
+        fs::write(&user_storage_path, "s01e02\ns01e01\n").unwrap();
+        fs::create_dir(s.build_tmp_path(&user_storage_path)).unwrap();
+
+        let result = s.append(&user_id, &ShowID::default(), seen("s01e03"));
+        assert!(matches!(result, Err(Error::FileError(_))));
+
+        assert_eq!(
+            fs::read_to_string(&user_storage_path).unwrap(),
+            "s01e02\ns01e01\n"
+        );
+    }
+
+    #[test]
+    fn store_append_fn_converts_legacy_file_to_journal() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let user_id = UserID::new(317);
+
+        let user_storage_path = s.build_user_storage_path(&user_id, &ShowID::default());
+        fs::create_dir_all(user_storage_path.parent().unwrap()).unwrap();
+        fs::write(&user_storage_path, "s01e02\ns01e01\n").unwrap();
+
+        let result = s.append(&user_id, &ShowID::default(), seen("s01e03"));
+        assert!(result.is_ok(), "result is error: {result:#?}");
+
+        assert!(!s.is_legacy_file(&user_storage_path).unwrap());
+        assert_eq!(
+            s.load(&user_id, &ShowID::default()).unwrap(),
+            vec![legacy("s01e01"), legacy("s01e02"), seen("s01e03")]
+        );
+    }
+
+    #[test]
+    fn store_append_fn_only_adds_a_line_to_journal_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let user_id = UserID::new(317);
+
+        s.append(&user_id, &ShowID::default(), seen("s01e01"))
+            .unwrap();
+        let user_storage_path = s.build_user_storage_path(&user_id, &ShowID::default());
+        let before = fs::read_to_string(&user_storage_path).unwrap();
+
+        s.append(&user_id, &ShowID::default(), seen("s01e02"))
+            .unwrap();
+
+        let after = fs::read_to_string(&user_storage_path).unwrap();
+        assert!(after.starts_with(&before));
+        assert_eq!(after.lines().count(), 2);
+    }
+
+    #[test]
+    fn store_append_fn_starts_new_line_after_torn_line() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let user_id = UserID::new(317);
+
+        s.append(&user_id, &ShowID::default(), seen("s01e01"))
+            .unwrap();
+        let user_storage_path = s.build_user_storage_path(&user_id, &ShowID::default());
+        // строка, оборванная при сбое во время записи
+        OpenOptions::new()
+            .append(true)
+            .open(&user_storage_path)
+            .unwrap()
+            .write_all(br#"{"code":"s01e05","seen_at":"2023-11"#)
+            .unwrap();
+
+        s.append(&user_id, &ShowID::default(), seen("s01e02"))
+            .unwrap();
+
+        assert_eq!(
+            s.load(&user_id, &ShowID::default()).unwrap(),
+            vec![seen("s01e01"), seen("s01e02")]
+        );
+    }
+
+    #[test]
+    fn store_create_directory_if_not_exists_fn_creates_multiple_directories() {
+        let s = build_store();
+
+        let tmpdir = TempDir::new().unwrap();
+
+        let path = tmpdir.path().join("some/path");
+        eprintln!("{}", path.to_string_lossy());
+        assert!(!path.exists());
+
+        let result = s.create_directory_if_not_exists(&path);
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn store_create_directory_if_not_exists_fn_does_not_error_if_directories_already_exists() {
+        let s = build_store();
+
+        // Setup temp directory
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let test_path = temp_dir.path().join("existing").join("subdirectory");
+
+        // First create the directory structure
+        fs::create_dir_all(&test_path).expect("Failed to create initial directory structure");
+
+        // Test the function - should not error even though dir exists
+        let result = s.create_directory_if_not_exists(&test_path);
+
+        // Verify
+        assert!(
+            result.is_ok(),
+            "Function should not error when directory already exists"
+        );
+
+        // Additional verification that directory still exists
+        assert!(
+            test_path.exists(),
+            "Directory should still exist after function call"
+        );
+        assert!(
+            test_path.is_dir(),
+            "Path should still be a directory after function call"
+        );
+    }
+
+    #[test]
+    fn store_list_fn_skips_files_not_belonging_to_users() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+
+        s.append(&UserID::new(7), &ShowID::default(), seen("s01e01"))
+            .unwrap();
+        let show_path = temp_dir.path().join("shows").join("friends");
+        File::create(show_path.join("not-a-user.txt")).unwrap();
+        File::create(show_path.join("42.json")).unwrap();
+        File::create(temp_dir.path().join("shows").join("13.txt")).unwrap();
+
+        let result = s.list();
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), vec![UserID::new(7)]);
+    }
+
+    #[test]
+    fn store_list_fn_returns_empty_list_if_storage_does_not_exist() {
+        let s = new(PathBuf::from("non_existing_directory"));
+
+        let result = s.list();
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn store_clear_fn_removes_existing_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+
+        let user_id = UserID::new(317);
+        let test_file_path = s.build_user_storage_path(&user_id, &ShowID::default());
+        fs::create_dir_all(test_file_path.parent().unwrap())
+            .expect("не удалось создать папку для тестового файла");
+        File::create(&test_file_path)
+            .expect("не удалось создать тестовый файл")
+            .write_all(b"test data")
+            .expect("не удалось записать в тестовый файл");
+
+        let result = s.clear(&user_id, &ShowID::default());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert!(!test_file_path.exists(), "File should be deleted");
+    }
+
+    #[test]
+    fn store_load_settings_fn_returns_defaults_for_corrupted_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let s = new(temp_dir.path().to_path_buf());
+        let user_id = UserID::new(317);
+
+        let settings_path = s.build_settings_path(&user_id);
+        fs::create_dir_all(settings_path.parent().unwrap()).unwrap();
+        fs::write(&settings_path, r#"{"show":"#).unwrap();
+
+        let result = s.load_settings(&user_id);
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), UserSettings::default());
+    }
+}
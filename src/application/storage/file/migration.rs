@@ -0,0 +1,322 @@
+//! Версии схемы файлового хранилища и миграции между ними.
+//!
+//! Версия записывается в файл `schema.json` в корне хранилища. Хранилище без этого файла,
+//! но с файлами пользователей, считается хранилищем версии 1.
+//!
+//! - версия 1: в файле пользователя по коду серии на строку, недавние серии наверху;
+//! - версия 2: журнал, в каждой строке JSON с кодом серии, временем и источником отметки;
+//! - версия 3: у каждого сериала своя папка `shows/{show_id}`, история из корня хранилища
+//!   относится к сериалу по умолчанию.
+
+use super::{Error, MigrationReport, SeenEpisodesStore, ShowID, Store, UserID};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SCHEMA_FILE_NAME: &str = "schema.json";
+
+/// Миграция со схемы версии `N` на `N + 1` лежит в этом списке под индексом `N - 1`.
+/// Принимает папку для резервных копий и возвращает число изменённых пользователей.
+type Migration = fn(&Store, &Path) -> Result<usize, Error>;
+
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+#[derive(Serialize, Deserialize)]
+struct SchemaFile {
+    schema_version: u32,
+}
+
+pub fn migrate(store: &Store) -> Result<MigrationReport, Error> {
+    let from_version = match read_schema_version(store)? {
+        Some(version) => version,
+        None if store.list()?.is_empty()
+            && store.list_users_in(&store.storage_path)?.is_empty() =>
+        {
+            // новое хранилище, переводить нечего
+            write_schema_version(store, SCHEMA_VERSION)?;
+            return Ok(MigrationReport::up_to_date(SCHEMA_VERSION));
+        }
+        None => 1,
+    };
+
+    if from_version > SCHEMA_VERSION {
+        return Err(Error::StorageSchemaError(format!(
+            "хранилище создано более новой версией бота: version={from_version}, supported={SCHEMA_VERSION}"
+        )));
+    }
+    if from_version == SCHEMA_VERSION {
+        return Ok(MigrationReport::up_to_date(from_version));
+    }
+
+    let backup_path = super::super::build_backup_path(&store.storage_path, from_version);
+
+    let mut migrated_users = 0;
+    for version in from_version..SCHEMA_VERSION {
+        let migration = MIGRATIONS[version as usize - 1];
+        // шаги затрагивают одних и тех же пользователей, поэтому не складываем их
+        migrated_users = migrated_users.max(migration(store, &backup_path)?);
+
+        // фиксируем каждый шаг, чтобы после сбоя продолжить с того же места
+        write_schema_version(store, version + 1)?;
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: SCHEMA_VERSION,
+        migrated_users,
+        backup_path: backup_path.exists().then_some(backup_path),
+    })
+}
+
+fn read_schema_version(store: &Store) -> Result<Option<u32>, Error> {
+    let content = match fs::read_to_string(store.storage_path.join(SCHEMA_FILE_NAME)) {
+        Ok(content) => content,
+        Err(err) => match err.kind() {
+            std::io::ErrorKind::NotFound => return Ok(None),
+            _ => return Err(Error::FileError(err)),
+        },
+    };
+
+    let schema: SchemaFile = serde_json::from_str(&content).map_err(|err| {
+        Error::StorageSchemaError(format!("не удалось прочитать {SCHEMA_FILE_NAME}: {err}"))
+    })?;
+
+    Ok(Some(schema.schema_version))
+}
+
+fn write_schema_version(store: &Store, schema_version: u32) -> Result<(), Error> {
+    store.create_directory_if_not_exists(&store.storage_path)?;
+
+    let content = serde_json::to_string(&SchemaFile { schema_version }).map_err(|err| {
+        Error::StorageSchemaError(format!("не удалось записать {SCHEMA_FILE_NAME}: {err}"))
+    })?;
+
+    store.write_file_atomically(
+        &store.storage_path.join(SCHEMA_FILE_NAME),
+        content.as_bytes(),
+    )?;
+
+    Ok(())
+}
+
+/// Переводит файлы пользователей из построчного списка кодов в журнал.
+fn migrate_v1_to_v2(store: &Store, backup_path: &Path) -> Result<usize, Error> {
+    let mut migrated_users = 0;
+
+    for user_id in store.list_users_in(&store.storage_path)? {
+        let path = build_root_user_storage_path(store, &user_id);
+        if !store.is_legacy_file(&path)? {
+            continue;
+        }
+
+        store.create_directory_if_not_exists(backup_path)?;
+        fs::copy(
+            &path,
+            backup_path.join(path.file_name().unwrap_or_default()),
+        )?;
+
+        let seen_episodes = store.read_db_from_file(&path)?;
+        store.save_db_to_file(seen_episodes, &path)?;
+
+        migrated_users += 1;
+    }
+
+    Ok(migrated_users)
+}
+
+/// Переносит файлы пользователей из корня хранилища в папку сериала по умолчанию.
+///
+/// Если в папке сериала у пользователя уже есть файл, миграция останавливается: какую
+/// из двух историй оставить, решает администратор.
+fn migrate_v2_to_v3(store: &Store, backup_path: &Path) -> Result<usize, Error> {
+    let mut migrated_users = 0;
+
+    for user_id in store.list_users_in(&store.storage_path)? {
+        let path = build_root_user_storage_path(store, &user_id);
+        let new_path = store.build_user_storage_path(&user_id, &ShowID::default());
+
+        if new_path.exists() {
+            return Err(Error::StorageSchemaError(format!(
+                "у пользователя есть история и в корне хранилища, и в папке сериала: from={}, to={}",
+                path.display(),
+                new_path.display()
+            )));
+        }
+
+        // при переходе сразу с версии 1 здесь уже лежит копия исходного файла, она ценнее
+        let backup_file_path = backup_path.join(path.file_name().unwrap_or_default());
+        if !backup_file_path.exists() {
+            store.create_directory_if_not_exists(backup_path)?;
+            fs::copy(&path, &backup_file_path)?;
+        }
+
+        if let Some(parent) = new_path.parent() {
+            store.create_directory_if_not_exists(parent)?;
+        }
+        fs::rename(&path, &new_path)?;
+
+        migrated_users += 1;
+    }
+
+    Ok(migrated_users)
+}
+
+/// Путь к файлу пользователя в схемах версий 1 и 2, когда сериал был только один.
+fn build_root_user_storage_path(store: &Store, user_id: &UserID) -> PathBuf {
+    store.storage_path.join(format!("{user_id}.txt"))
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::application::{Episode, SeenEpisode, SeenEpisodeSource};
+
+    fn build_store_in_temp_dir() -> (Store, TempDir) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+        (super::super::new(temp_dir.path().to_path_buf()), temp_dir)
+    }
+
+    #[test]
+    fn migrate_fn_marks_new_storage_with_current_version() {
+        let (store, temp_dir) = build_store_in_temp_dir();
+
+        let result = migrate(&store);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), MigrationReport::up_to_date(SCHEMA_VERSION));
+        assert_eq!(read_schema_version(&store).unwrap(), Some(SCHEMA_VERSION));
+        assert!(!temp_dir.path().join("backups").exists());
+    }
+
+    #[test]
+    fn migrate_fn_converts_legacy_files_and_keeps_backups() {
+        let (store, temp_dir) = build_store_in_temp_dir();
+        fs::write(temp_dir.path().join("317.txt"), "s01e02\ns01e01\n").unwrap();
+        fs::write(temp_dir.path().join("42.txt"), "s03e03\n").unwrap();
+
+        let result = migrate(&store);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let report = result.unwrap();
+        assert_eq!(report.from_version, 1);
+        assert_eq!(report.to_version, SCHEMA_VERSION);
+        assert_eq!(report.migrated_users, 2);
+
+        let backup_path = report.backup_path.expect("backup should be made");
+        assert_eq!(
+            fs::read_to_string(backup_path.join("317.txt")).unwrap(),
+            "s01e02\ns01e01\n"
+        );
+        assert_eq!(
+            fs::read_to_string(backup_path.join("42.txt")).unwrap(),
+            "s03e03\n"
+        );
+
+        assert!(!temp_dir.path().join("317.txt").exists());
+        let user_storage_path =
+            store.build_user_storage_path(&UserID::new(317), &ShowID::default());
+        assert!(!store.is_legacy_file(&user_storage_path).unwrap());
+        assert_eq!(
+            store.load(&UserID::new(317), &ShowID::default()).unwrap(),
+            vec![
+                SeenEpisode::new(
+                    Episode::try_from("s01e01").unwrap(),
+                    None,
+                    SeenEpisodeSource::Legacy
+                ),
+                SeenEpisode::new(
+                    Episode::try_from("s01e02").unwrap(),
+                    None,
+                    SeenEpisodeSource::Legacy
+                ),
+            ]
+        );
+        assert_eq!(read_schema_version(&store).unwrap(), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn migrate_fn_does_nothing_when_storage_is_up_to_date() {
+        let (store, temp_dir) = build_store_in_temp_dir();
+        fs::write(temp_dir.path().join("317.txt"), "s01e01\n").unwrap();
+        migrate(&store).unwrap();
+
+        let result = migrate(&store);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), MigrationReport::up_to_date(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn migrate_fn_refuses_storage_from_newer_version() {
+        let (store, _temp_dir) = build_store_in_temp_dir();
+        write_schema_version(&store, SCHEMA_VERSION + 1).unwrap();
+
+        let result = migrate(&store);
+
+        assert!(matches!(result, Err(Error::StorageSchemaError(_))));
+    }
+
+    #[test]
+    fn migrate_fn_moves_journal_files_to_default_show() {
+        let (store, temp_dir) = build_store_in_temp_dir();
+        let journal = r#"{"code":"s01e01","seen_at":null,"source":"legacy"}"#;
+        fs::write(temp_dir.path().join("317.txt"), format!("{journal}\n")).unwrap();
+        write_schema_version(&store, 2).unwrap();
+
+        let result = migrate(&store);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        let report = result.unwrap();
+        assert_eq!(report.from_version, 2);
+        assert_eq!(report.migrated_users, 1);
+        let backup_path = report.backup_path.expect("backup should be made");
+        assert_eq!(
+            fs::read_to_string(backup_path.join("317.txt")).unwrap(),
+            format!("{journal}\n")
+        );
+
+        assert!(!temp_dir.path().join("317.txt").exists());
+        assert_eq!(store.list().unwrap(), vec![UserID::new(317)]);
+        assert_eq!(
+            store.load(&UserID::new(317), &ShowID::default()).unwrap(),
+            vec![SeenEpisode::new(
+                Episode::try_from("s01e01").unwrap(),
+                None,
+                SeenEpisodeSource::Legacy
+            )]
+        );
+    }
+
+    #[test]
+    fn migrate_fn_refuses_to_overwrite_history_of_default_show() {
+        let (store, temp_dir) = build_store_in_temp_dir();
+        let root_journal = r#"{"code":"s01e01","seen_at":null,"source":"legacy"}"#;
+        let show_journal = r#"{"code":"s02e02","seen_at":null,"source":"legacy"}"#;
+        fs::write(temp_dir.path().join("317.txt"), format!("{root_journal}\n")).unwrap();
+        let user_storage_path =
+            store.build_user_storage_path(&UserID::new(317), &ShowID::default());
+        fs::create_dir_all(user_storage_path.parent().unwrap()).unwrap();
+        fs::write(&user_storage_path, format!("{show_journal}\n")).unwrap();
+        write_schema_version(&store, 2).unwrap();
+
+        let result = migrate(&store);
+
+        assert!(matches!(result, Err(Error::StorageSchemaError(_))));
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("317.txt")).unwrap(),
+            format!("{root_journal}\n")
+        );
+        assert_eq!(
+            fs::read_to_string(&user_storage_path).unwrap(),
+            format!("{show_journal}\n")
+        );
+        assert_eq!(read_schema_version(&store).unwrap(), Some(2));
+    }
+}
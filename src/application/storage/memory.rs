@@ -0,0 +1,230 @@
+use super::{
+    Episode, Error, MigrationReport, OFFERS_RETENTION, OfferedEpisode, SeenEpisode,
+    SeenEpisodesStore, ShowID, UserID, UserSettings,
+};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+pub fn new() -> Store {
+    Store {
+        data: Mutex::new(Data::default()),
+    }
+}
+
+/// Хранит просмотренные серии в памяти процесса. Данные пропадают при перезапуске.
+pub struct Store {
+    data: Mutex<Data>,
+}
+
+#[derive(Default)]
+struct Data {
+    seen_episodes: HashMap<(UserID, ShowID), Vec<SeenEpisode>>,
+    settings: HashMap<UserID, UserSettings>,
+    archives: HashMap<(UserID, ShowID), Vec<Vec<SeenEpisode>>>,
+    offers: HashMap<(UserID, ShowID), Vec<OfferedEpisode>>,
+}
+
+impl SeenEpisodesStore for Store {
+    fn load(&self, user_id: &UserID, show_id: &ShowID) -> Result<Vec<SeenEpisode>, Error> {
+        Ok(self
+            .data()
+            .seen_episodes
+            .get(&(*user_id, show_id.clone()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn append(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        seen_episode: SeenEpisode,
+    ) -> Result<(), Error> {
+        self.data()
+            .seen_episodes
+            .entry((*user_id, show_id.clone()))
+            .or_default()
+            .push(seen_episode);
+
+        Ok(())
+    }
+
+    fn append_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        mut seen_episodes: Vec<SeenEpisode>,
+    ) -> Result<(), Error> {
+        if seen_episodes.is_empty() {
+            return Ok(());
+        }
+
+        self.data()
+            .seen_episodes
+            .entry((*user_id, show_id.clone()))
+            .or_default()
+            .append(&mut seen_episodes);
+
+        Ok(())
+    }
+
+    fn remove_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episodes: &[Episode],
+    ) -> Result<(), Error> {
+        let key = (*user_id, show_id.clone());
+        let mut data = self.data();
+
+        let Some(seen_episodes) = data.seen_episodes.get_mut(&key) else {
+            return Ok(());
+        };
+        seen_episodes.retain(|seen_episode| !episodes.contains(seen_episode.episode()));
+        if seen_episodes.is_empty() {
+            data.seen_episodes.remove(&key);
+        }
+
+        Ok(())
+    }
+
+    fn remove_last(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episode: Option<&Episode>,
+    ) -> Result<Option<SeenEpisode>, Error> {
+        let key = (*user_id, show_id.clone());
+        let mut data = self.data();
+
+        let Some(seen_episodes) = data.seen_episodes.get_mut(&key) else {
+            return Ok(None);
+        };
+        let Some(index) = seen_episodes.iter().rposition(|seen_episode| {
+            episode.is_none_or(|episode| seen_episode.episode() == episode)
+        }) else {
+            return Ok(None);
+        };
+
+        let removed = seen_episodes.remove(index);
+        // как и после очистки, пользователь без отметок не должен попадать в список
+        if seen_episodes.is_empty() {
+            data.seen_episodes.remove(&key);
+        }
+
+        Ok(Some(removed))
+    }
+
+    fn list(&self) -> Result<Vec<UserID>, Error> {
+        let mut user_ids: Vec<UserID> = self
+            .data()
+            .seen_episodes
+            .keys()
+            .map(|(user_id, _)| *user_id)
+            .collect();
+        user_ids.sort();
+        user_ids.dedup();
+
+        Ok(user_ids)
+    }
+
+    fn clear(&self, user_id: &UserID, show_id: &ShowID) -> Result<(), Error> {
+        self.data()
+            .seen_episodes
+            .remove(&(*user_id, show_id.clone()));
+
+        Ok(())
+    }
+
+    fn archive(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error> {
+        let key = (*user_id, show_id.clone());
+        let mut data = self.data();
+
+        let seen_episodes = data.seen_episodes.remove(&key).unwrap_or_default();
+        let archive = data.archives.entry(key).or_default();
+        if !seen_episodes.is_empty() {
+            archive.push(seen_episodes);
+        }
+
+        Ok(archive.len() as u32)
+    }
+
+    fn completed_cycles(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error> {
+        Ok(self
+            .data()
+            .archives
+            .get(&(*user_id, show_id.clone()))
+            .map_or(0, |archive| archive.len() as u32))
+    }
+
+    fn append_offer(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        offered_episode: OfferedEpisode,
+    ) -> Result<(), Error> {
+        let mut data = self.data();
+        let offers = data.offers.entry((*user_id, show_id.clone())).or_default();
+        offers.push(offered_episode);
+        offers.drain(..offers.len().saturating_sub(OFFERS_RETENTION));
+
+        Ok(())
+    }
+
+    fn load_recent_offers(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        limit: usize,
+    ) -> Result<Vec<OfferedEpisode>, Error> {
+        let data = self.data();
+        let Some(offers) = data.offers.get(&(*user_id, show_id.clone())) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(offers[offers.len().saturating_sub(limit)..].to_vec())
+    }
+
+    fn load_settings(&self, user_id: &UserID) -> Result<UserSettings, Error> {
+        Ok(self
+            .data()
+            .settings
+            .get(user_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn save_settings(&self, user_id: &UserID, settings: &UserSettings) -> Result<(), Error> {
+        self.data().settings.insert(*user_id, settings.clone());
+
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<MigrationReport, Error> {
+        // данные живут только в памяти процесса, мигрировать нечего
+        Ok(MigrationReport::up_to_date(0))
+    }
+}
+
+impl Store {
+    fn data(&self) -> MutexGuard<'_, Data> {
+        // все изменения под мьютексом атомарны, поэтому после паники данные остаются целыми
+        self.data
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application::storage::test_suite::seen_episodes_store_test_suite;
+
+    fn build_store() -> (Store, ()) {
+        (new(), ())
+    }
+
+    seen_episodes_store_test_suite!(build_store);
+}
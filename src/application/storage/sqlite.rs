@@ -0,0 +1,752 @@
+use super::{
+    Error, MigrationReport, OFFERS_RETENTION, OfferedEpisode, SeenEpisode, SeenEpisodesStore,
+    ShowID, UserID, UserSettings,
+};
+use crate::application::{Episode, SeenEpisodeSource};
+use chrono::DateTime;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// Миграции схемы базы. Номер последней применённой хранится в `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE IF NOT EXISTS seen_episodes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    episode_code TEXT NOT NULL,
+    seen_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS seen_episodes_user_id_idx ON seen_episodes (user_id);
+"#,
+    r#"
+CREATE TABLE seen_episodes_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    episode_code TEXT NOT NULL,
+    seen_at INTEGER,
+    source TEXT NOT NULL
+);
+INSERT INTO seen_episodes_new (id, user_id, episode_code, seen_at, source)
+    SELECT id, user_id, episode_code, seen_at, 'bot' FROM seen_episodes;
+DROP TABLE seen_episodes;
+ALTER TABLE seen_episodes_new RENAME TO seen_episodes;
+CREATE INDEX seen_episodes_user_id_idx ON seen_episodes (user_id);
+"#,
+    r#"
+ALTER TABLE seen_episodes ADD COLUMN show_id TEXT NOT NULL DEFAULT 'friends';
+DROP INDEX seen_episodes_user_id_idx;
+CREATE INDEX seen_episodes_user_id_show_id_idx ON seen_episodes (user_id, show_id);
+CREATE TABLE user_settings (
+    user_id INTEGER PRIMARY KEY,
+    settings TEXT NOT NULL
+);
+"#,
+    r#"
+ALTER TABLE seen_episodes ADD COLUMN cycle INTEGER;
+"#,
+    r#"
+CREATE TABLE offered_episodes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    show_id TEXT NOT NULL,
+    episode_code TEXT NOT NULL,
+    offered_at INTEGER NOT NULL
+);
+CREATE INDEX offered_episodes_user_id_show_id_idx ON offered_episodes (user_id, show_id);
+"#,
+];
+
+pub fn new(database_path: &Path) -> Result<Store, Error> {
+    if let Some(parent) = database_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let connection = Connection::open(database_path)?;
+
+    Ok(Store {
+        connection: Mutex::new(connection),
+        database_path: Some(database_path.to_path_buf()),
+    })
+}
+
+/// Хранит просмотренные серии в таблице `seen_episodes` базы SQLite, а настройки
+/// пользователей в виде JSON в таблице `user_settings`. Предложенные ботом серии
+/// записываются в таблицу `offered_episodes`, где для каждого пользователя и сериала
+/// остаются только [`OFFERS_RETENTION`] последних строк.
+///
+/// У отметок текущего круга просмотра `cycle` равен `NULL`, у отметок из архива это номер
+/// завершённого круга.
+///
+/// Схема базы создаётся и обновляется в [`SeenEpisodesStore::migrate`].
+pub struct Store {
+    connection: Mutex<Connection>,
+    /// Путь к файлу базы, если она не в памяти. Нужен для резервных копий перед миграцией.
+    database_path: Option<PathBuf>,
+}
+
+impl SeenEpisodesStore for Store {
+    fn load(&self, user_id: &UserID, show_id: &ShowID) -> Result<Vec<SeenEpisode>, Error> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT id, episode_code, seen_at, source FROM seen_episodes WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL ORDER BY id",
+        )?;
+
+        let rows = statement
+            .query_map(params![user_id.0, show_id.as_str()], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut seen_episodes = Vec::with_capacity(rows.len());
+        for (id, code, seen_at, source) in rows {
+            match parse_seen_episode(&code, seen_at, &source) {
+                Ok(seen_episode) => seen_episodes.push(seen_episode),
+                // одна испорченная запись не должна лишать пользователя всей истории
+                Err(err) => tracing::warn!(
+                    id = id,
+                    error = err.to_string(),
+                    "пропускаем повреждённую запись в таблице seen_episodes"
+                ),
+            }
+        }
+
+        Ok(seen_episodes)
+    }
+
+    fn append(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        seen_episode: SeenEpisode,
+    ) -> Result<(), Error> {
+        self.connection()
+            .prepare_cached(
+                "INSERT INTO seen_episodes (user_id, show_id, episode_code, seen_at, source) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![
+                user_id.0,
+                show_id.as_str(),
+                seen_episode.episode().code(),
+                seen_episode.seen_at().map(|seen_at| seen_at.timestamp()),
+                seen_episode.source().as_str(),
+            ])?;
+
+        Ok(())
+    }
+
+    fn append_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        seen_episodes: Vec<SeenEpisode>,
+    ) -> Result<(), Error> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO seen_episodes (user_id, show_id, episode_code, seen_at, source) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for seen_episode in &seen_episodes {
+                statement.execute(params![
+                    user_id.0,
+                    show_id.as_str(),
+                    seen_episode.episode().code(),
+                    seen_episode.seen_at().map(|seen_at| seen_at.timestamp()),
+                    seen_episode.source().as_str(),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn remove_all(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episodes: &[Episode],
+    ) -> Result<(), Error> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+
+        {
+            let mut statement = transaction.prepare_cached(
+                "DELETE FROM seen_episodes WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL AND episode_code = ?3",
+            )?;
+            for episode in episodes {
+                statement.execute(params![user_id.0, show_id.as_str(), episode.code()])?;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn remove_last(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episode: Option<&Episode>,
+    ) -> Result<Option<SeenEpisode>, Error> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+
+        let row = transaction
+            .prepare_cached(
+                "SELECT id, episode_code, seen_at, source FROM seen_episodes WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL AND (?3 IS NULL OR episode_code = ?3) ORDER BY id DESC LIMIT 1",
+            )?
+            .query_row(
+                params![user_id.0, show_id.as_str(), episode.map(Episode::code)],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<i64>>(2)?,
+                        row.get::<_, String>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((id, code, seen_at, source)) = row else {
+            return Ok(None);
+        };
+
+        transaction
+            .prepare_cached("DELETE FROM seen_episodes WHERE id = ?1")?
+            .execute(params![id])?;
+        transaction.commit()?;
+
+        parse_seen_episode(&code, seen_at, &source).map(Some)
+    }
+
+    fn list(&self) -> Result<Vec<UserID>, Error> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare_cached("SELECT DISTINCT user_id FROM seen_episodes ORDER BY user_id")?;
+
+        let user_ids = statement
+            .query_map([], |row| row.get(0).map(UserID::new))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(user_ids)
+    }
+
+    fn clear(&self, user_id: &UserID, show_id: &ShowID) -> Result<(), Error> {
+        self.connection()
+            .prepare_cached(
+                "DELETE FROM seen_episodes WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL",
+            )?
+            .execute(params![user_id.0, show_id.as_str()])?;
+
+        Ok(())
+    }
+
+    fn archive(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+
+        let completed_cycles = count_completed_cycles(&transaction, user_id, show_id)?;
+        let archived = transaction
+            .prepare_cached(
+                "UPDATE seen_episodes SET cycle = ?3 WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL",
+            )?
+            .execute(params![user_id.0, show_id.as_str(), completed_cycles + 1])?;
+        transaction.commit()?;
+
+        match archived {
+            0 => Ok(completed_cycles),
+            _ => Ok(completed_cycles + 1),
+        }
+    }
+
+    fn archive_completed(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        episodes: &[Episode],
+    ) -> Result<Option<u32>, Error> {
+        let mut connection = self.connection();
+        // IMMEDIATE сразу берёт блокировку на запись, чтобы между проверкой и переносом
+        // другой процесс не успел изменить текущий круг
+        let transaction =
+            connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let seen_codes = transaction
+            .prepare_cached(
+                "SELECT DISTINCT episode_code FROM seen_episodes WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL",
+            )?
+            .query_map(params![user_id.0, show_id.as_str()], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<HashSet<String>, _>>()?;
+        if !episodes
+            .iter()
+            .all(|episode| seen_codes.contains(episode.code()))
+        {
+            return Ok(None);
+        }
+
+        let completed_cycles = count_completed_cycles(&transaction, user_id, show_id)?;
+        let archived = transaction
+            .prepare_cached(
+                "UPDATE seen_episodes SET cycle = ?3 WHERE user_id = ?1 AND show_id = ?2 AND cycle IS NULL",
+            )?
+            .execute(params![user_id.0, show_id.as_str(), completed_cycles + 1])?;
+        transaction.commit()?;
+
+        match archived {
+            0 => Ok(Some(completed_cycles)),
+            _ => Ok(Some(completed_cycles + 1)),
+        }
+    }
+
+    fn completed_cycles(&self, user_id: &UserID, show_id: &ShowID) -> Result<u32, Error> {
+        count_completed_cycles(&self.connection(), user_id, show_id)
+    }
+
+    fn append_offer(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        offered_episode: OfferedEpisode,
+    ) -> Result<(), Error> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction
+            .prepare_cached(
+                "INSERT INTO offered_episodes (user_id, show_id, episode_code, offered_at) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![
+                user_id.0,
+                show_id.as_str(),
+                offered_episode.episode().code(),
+                offered_episode.offered_at().timestamp(),
+            ])?;
+        transaction
+            .prepare_cached(
+                "DELETE FROM offered_episodes WHERE user_id = ?1 AND show_id = ?2 AND id <= (SELECT id FROM offered_episodes WHERE user_id = ?1 AND show_id = ?2 ORDER BY id DESC LIMIT 1 OFFSET ?3)",
+            )?
+            .execute(params![
+                user_id.0,
+                show_id.as_str(),
+                OFFERS_RETENTION as i64
+            ])?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn load_recent_offers(
+        &self,
+        user_id: &UserID,
+        show_id: &ShowID,
+        limit: usize,
+    ) -> Result<Vec<OfferedEpisode>, Error> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT id, episode_code, offered_at FROM offered_episodes WHERE user_id = ?1 AND show_id = ?2 ORDER BY id DESC LIMIT ?3",
+        )?;
+
+        let rows = statement
+            .query_map(params![user_id.0, show_id.as_str(), limit as i64], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut offers = Vec::with_capacity(rows.len());
+        for (id, code, offered_at) in rows.into_iter().rev() {
+            let offered_episode = Episode::try_from(code.as_str()).and_then(|episode| {
+                let offered_at = DateTime::from_timestamp(offered_at, 0).ok_or_else(|| {
+                    Error::StorageSchemaError(format!(
+                        "некорректное время предложения: {offered_at}"
+                    ))
+                })?;
+
+                Ok(OfferedEpisode::new(episode, offered_at))
+            });
+
+            match offered_episode {
+                Ok(offered_episode) => offers.push(offered_episode),
+                Err(err) => tracing::warn!(
+                    id = id,
+                    error = err.to_string(),
+                    "пропускаем повреждённую запись в таблице offered_episodes"
+                ),
+            }
+        }
+
+        Ok(offers)
+    }
+
+    fn load_settings(&self, user_id: &UserID) -> Result<UserSettings, Error> {
+        let settings: Option<String> = self
+            .connection()
+            .prepare_cached("SELECT settings FROM user_settings WHERE user_id = ?1")?
+            .query_row(params![user_id.0], |row| row.get(0))
+            .optional()?;
+
+        let Some(settings) = settings else {
+            return Ok(UserSettings::default());
+        };
+
+        match serde_json::from_str(&settings) {
+            Ok(settings) => Ok(settings),
+            // испорченные настройки не должны мешать пользоваться ботом
+            Err(err) => {
+                tracing::warn!(
+                    user_id = user_id.0,
+                    error = err.to_string(),
+                    "не удалось прочитать настройки пользователя, используем настройки по умолчанию"
+                );
+
+                Ok(UserSettings::default())
+            }
+        }
+    }
+
+    fn save_settings(&self, user_id: &UserID, settings: &UserSettings) -> Result<(), Error> {
+        let settings = serde_json::to_string(settings).map_err(std::io::Error::from)?;
+
+        self.connection()
+            .prepare_cached(
+                "INSERT INTO user_settings (user_id, settings) VALUES (?1, ?2) ON CONFLICT (user_id) DO UPDATE SET settings = excluded.settings",
+            )?
+            .execute(params![user_id.0, settings])?;
+
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<MigrationReport, Error> {
+        let mut connection = self.connection();
+        let target_version = MIGRATIONS.len() as u32;
+
+        let from_version: u32 =
+            connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if from_version > target_version {
+            return Err(Error::StorageSchemaError(format!(
+                "база создана более новой версией бота: version={from_version}, supported={target_version}"
+            )));
+        }
+        if from_version == target_version {
+            return Ok(MigrationReport::up_to_date(from_version));
+        }
+
+        // в только что созданной базе ещё нечего сохранять
+        let is_empty_database: bool =
+            connection.query_row("SELECT COUNT(*) = 0 FROM sqlite_master", [], |row| {
+                row.get(0)
+            })?;
+        let backup_path = match &self.database_path {
+            Some(database_path) if !is_empty_database => {
+                let backup_dir = super::build_backup_path(
+                    database_path.parent().unwrap_or(Path::new("")),
+                    from_version,
+                );
+                fs::create_dir_all(&backup_dir)?;
+
+                let backup_path = backup_dir.join(database_path.file_name().unwrap_or_default());
+                connection.execute("VACUUM INTO ?1", params![backup_path.to_string_lossy()])?;
+
+                Some(backup_path)
+            }
+            _ => None,
+        };
+
+        let transaction = connection.transaction()?;
+        for migration in MIGRATIONS.iter().skip(from_version as usize) {
+            transaction.execute_batch(migration)?;
+        }
+        transaction.pragma_update(None, "user_version", target_version)?;
+        transaction.commit()?;
+
+        let migrated_users: usize = connection.query_row(
+            "SELECT COUNT(DISTINCT user_id) FROM seen_episodes",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(MigrationReport {
+            from_version,
+            to_version: target_version,
+            migrated_users,
+            backup_path,
+        })
+    }
+}
+
+fn parse_seen_episode(
+    code: &str,
+    seen_at: Option<i64>,
+    source: &str,
+) -> Result<SeenEpisode, Error> {
+    let episode = Episode::try_from(code)?;
+    let source = SeenEpisodeSource::from_name(source).ok_or_else(|| {
+        Error::StorageSchemaError(format!("неизвестный источник отметки: {source}"))
+    })?;
+    let seen_at = seen_at.and_then(|seen_at| DateTime::from_timestamp(seen_at, 0));
+
+    Ok(SeenEpisode::new(episode, seen_at, source))
+}
+
+fn count_completed_cycles(
+    connection: &Connection,
+    user_id: &UserID,
+    show_id: &ShowID,
+) -> Result<u32, Error> {
+    let completed_cycles = connection
+        .prepare_cached(
+            "SELECT COUNT(DISTINCT cycle) FROM seen_episodes WHERE user_id = ?1 AND show_id = ?2",
+        )?
+        .query_row(params![user_id.0, show_id.as_str()], |row| row.get(0))?;
+
+    Ok(completed_cycles)
+}
+
+impl Store {
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // соединение не остаётся в неконсистентном состоянии после паники,
+        // поэтому отравленный мьютекс можно спокойно использовать дальше
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::application::storage::test_suite::seen_episodes_store_test_suite;
+
+    fn build_store_with_connection(connection: Connection) -> Store {
+        Store {
+            connection: Mutex::new(connection),
+            database_path: None,
+        }
+    }
+
+    fn build_store() -> (Store, ()) {
+        let connection = Connection::open_in_memory().expect("Failed to open in-memory database");
+        let store = build_store_with_connection(connection);
+        store.migrate().expect("Failed to migrate store");
+
+        (store, ())
+    }
+
+    seen_episodes_store_test_suite!(build_store);
+
+    #[test]
+    fn store_new_fn_creates_database_file_with_parent_directories() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database_path = temp_dir.path().join("some/path/seen_episodes.sqlite3");
+
+        let result = new(&database_path);
+
+        assert!(result.is_ok(), "result is error: {:#?}", result.err());
+        assert!(database_path.exists());
+    }
+
+    #[test]
+    fn store_keeps_data_between_connections() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database_path = temp_dir.path().join("seen_episodes.sqlite3");
+        let user_id = UserID::new(317);
+
+        let seen_episode = SeenEpisode::new(
+            Episode::try_from("s01e01").unwrap(),
+            DateTime::from_timestamp(1_700_000_000, 0),
+            SeenEpisodeSource::Bot,
+        );
+
+        let store = new(&database_path).unwrap();
+        store.migrate().unwrap();
+        store
+            .append(&user_id, &ShowID::default(), seen_episode.clone())
+            .unwrap();
+        drop(store);
+
+        let store = new(&database_path).unwrap();
+        store.migrate().unwrap();
+        let result = store.load(&user_id, &ShowID::default());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), vec![seen_episode]);
+    }
+
+    #[test]
+    fn store_upgrades_database_created_with_first_schema_version() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection
+            .execute(
+                "INSERT INTO seen_episodes (user_id, episode_code, seen_at) VALUES (317, 's01e01', 1700000000)",
+                [],
+            )
+            .unwrap();
+
+        connection.pragma_update(None, "user_version", 1).unwrap();
+        let store = build_store_with_connection(connection);
+
+        let report = store.migrate();
+        assert!(report.is_ok(), "report is error: {report:#?}");
+        assert_eq!(
+            report.unwrap(),
+            MigrationReport {
+                from_version: 1,
+                to_version: MIGRATIONS.len() as u32,
+                migrated_users: 1,
+                backup_path: None,
+            }
+        );
+
+        let result = store.load(&UserID::new(317), &ShowID::default());
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap(),
+            vec![SeenEpisode::new(
+                Episode::try_from("s01e01").unwrap(),
+                DateTime::from_timestamp(1_700_000_000, 0),
+                SeenEpisodeSource::Bot,
+            )]
+        );
+
+        let user_version: usize = store
+            .connection()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(user_version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn store_migrate_fn_upgrades_database_created_before_schema_versioning() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        let store = build_store_with_connection(connection);
+
+        let report = store.migrate();
+
+        assert!(report.is_ok(), "report is error: {report:#?}");
+        assert_eq!(report.unwrap().to_version, MIGRATIONS.len() as u32);
+    }
+
+    #[test]
+    fn store_migrate_fn_backs_up_database_file_before_upgrade() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database_path = temp_dir.path().join("seen_episodes.sqlite3");
+        {
+            // база, созданная до появления версий схемы
+            let connection = Connection::open(&database_path).unwrap();
+            connection.execute_batch(MIGRATIONS[0]).unwrap();
+            connection
+                .execute(
+                    "INSERT INTO seen_episodes (user_id, episode_code, seen_at) VALUES (317, 's01e01', 1700000000)",
+                    [],
+                )
+                .unwrap();
+        }
+
+        let report = new(&database_path).unwrap().migrate().unwrap();
+
+        let backup_path = report.backup_path.expect("backup should be made");
+        assert!(backup_path.starts_with(temp_dir.path().join("backups")));
+
+        let backup = Connection::open(&backup_path).unwrap();
+        let user_version: u32 = backup
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        let episode_code: String = backup
+            .query_row("SELECT episode_code FROM seen_episodes", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(user_version, 0);
+        assert_eq!(episode_code, "s01e01");
+    }
+
+    #[test]
+    fn store_migrate_fn_does_not_back_up_new_database() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let database_path = temp_dir.path().join("seen_episodes.sqlite3");
+
+        let report = new(&database_path).unwrap().migrate().unwrap();
+
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.backup_path, None);
+        assert!(!temp_dir.path().join("backups").exists());
+    }
+
+    #[test]
+    fn store_migrate_fn_refuses_database_from_newer_version() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        let store = build_store_with_connection(connection);
+
+        let result = store.migrate();
+
+        assert!(matches!(result, Err(Error::StorageSchemaError(_))));
+    }
+
+    #[test]
+    fn store_load_fn_skips_corrupted_rows() {
+        let (store, _) = build_store();
+        store
+            .connection()
+            .execute_batch(
+                r#"
+INSERT INTO seen_episodes (user_id, episode_code, seen_at, source) VALUES (317, 's01e01', 1700000000, 'bot');
+INSERT INTO seen_episodes (user_id, episode_code, seen_at, source) VALUES (317, 'xyz', 1700000000, 'bot');
+INSERT INTO seen_episodes (user_id, episode_code, seen_at, source) VALUES (317, 's01e02', 1700000000, 'alien');
+INSERT INTO seen_episodes (user_id, episode_code, seen_at, source) VALUES (317, 's01e03', NULL, 'legacy');
+"#,
+            )
+            .unwrap();
+
+        let result = store.load(&UserID::new(317), &ShowID::default());
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result
+                .unwrap()
+                .iter()
+                .map(|seen_episode| seen_episode.episode().code().to_string())
+                .collect::<Vec<_>>(),
+            vec!["s01e01", "s01e03"]
+        );
+    }
+
+    #[test]
+    fn store_load_settings_fn_returns_defaults_for_corrupted_row() {
+        let (store, _) = build_store();
+        store
+            .connection()
+            .execute(
+                "INSERT INTO user_settings (user_id, settings) VALUES (317, '{\"show\":')",
+                [],
+            )
+            .unwrap();
+
+        let result = store.load_settings(&UserID::new(317));
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), UserSettings::default());
+    }
+}
@@ -0,0 +1,552 @@
+//! Общий набор тестов, который должна проходить каждая реализация [`SeenEpisodesStore`].
+//!
+//! Подключается в модуле реализации макросом [`seen_episodes_store_test_suite`], которому
+//! передаётся функция, возвращающая хранилище и объект, живущий до конца теста
+//! (например, временную папку).
+
+use super::{OFFERS_RETENTION, SeenEpisodesStore};
+use crate::application::{
+    Episode, OfferedEpisode, SeasonFilter, SeenEpisode, SeenEpisodeSource, ShowID, UserID,
+    UserSettings,
+};
+use chrono::{DateTime, TimeDelta};
+
+macro_rules! seen_episodes_store_test_suite {
+    ($build_store:ident) => {
+        $crate::application::storage::test_suite::seen_episodes_store_test_suite!(
+            $build_store;
+            load_returns_empty_list_for_unknown_user,
+            append_keeps_viewing_order,
+            append_keeps_seen_at_and_source,
+            users_are_kept_separate,
+            list_returns_users_with_stored_data,
+            clear_removes_only_given_user,
+            clear_does_not_fail_for_unknown_user,
+            shows_are_kept_separate,
+            clear_removes_only_given_show,
+            list_counts_user_with_several_shows_once,
+            load_settings_returns_defaults_for_unknown_user,
+            save_settings_overwrites_previous_settings,
+            archive_starts_new_cycle,
+            archive_skips_empty_cycle,
+            archive_completed_skips_incomplete_cycle,
+            archive_keeps_other_shows_and_users,
+            clear_keeps_completed_cycles,
+            load_recent_offers_returns_last_offers_in_order,
+            offers_are_kept_separate,
+            append_offer_keeps_only_retained_offers,
+            remove_last_removes_latest_mark_of_episode,
+            remove_last_without_episode_removes_latest_mark,
+            remove_last_keeps_completed_cycles,
+            append_all_adds_marks_in_order,
+            remove_all_removes_every_mark_of_given_episodes,
+        );
+    };
+    ($build_store:ident; $($name:ident),+ $(,)?) => {
+        mod test_suite {
+            $(
+                #[test]
+                fn $name() {
+                    let (store, _guard) = super::$build_store();
+                    $crate::application::storage::test_suite::$name(&store);
+                }
+            )+
+        }
+    };
+}
+pub(crate) use seen_episodes_store_test_suite;
+
+fn seen(code: &str) -> SeenEpisode {
+    // время без долей секунды, чтобы все хранилища сохраняли его без потерь
+    let seen_at = DateTime::from_timestamp(1_700_000_000, 0).expect("valid timestamp");
+
+    SeenEpisode::new(
+        Episode::try_from(code).unwrap(),
+        Some(seen_at),
+        SeenEpisodeSource::Bot,
+    )
+}
+
+fn friends() -> ShowID {
+    ShowID::new("friends")
+}
+
+fn office() -> ShowID {
+    ShowID::new("the-office")
+}
+
+fn episodes(seen_episodes: Vec<SeenEpisode>) -> Vec<Episode> {
+    seen_episodes
+        .iter()
+        .map(|seen_episode| seen_episode.episode().clone())
+        .collect()
+}
+
+fn codes(codes: &[&str]) -> Vec<Episode> {
+    codes
+        .iter()
+        .map(|&code| Episode::try_from(code).unwrap())
+        .collect()
+}
+
+pub fn load_returns_empty_list_for_unknown_user(store: &dyn SeenEpisodesStore) {
+    let result = store.load(&UserID::new(317), &friends());
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), Vec::<SeenEpisode>::new());
+}
+
+pub fn append_keeps_viewing_order(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+
+    for code in ["s01e02", "s05e11", "s01e01"] {
+        store.append(&user_id, &friends(), seen(code)).unwrap();
+    }
+
+    let result = store.load(&user_id, &friends());
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(
+        episodes(result.unwrap()),
+        codes(&["s01e02", "s05e11", "s01e01"])
+    );
+}
+
+pub fn append_keeps_seen_at_and_source(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    let first = seen("s01e01");
+    let second = SeenEpisode::new(
+        Episode::try_from("s01e02").unwrap(),
+        first.seen_at().map(|seen_at| seen_at + TimeDelta::days(1)),
+        SeenEpisodeSource::Bot,
+    );
+    let legacy = SeenEpisode::new(
+        Episode::try_from("s01e03").unwrap(),
+        None,
+        SeenEpisodeSource::Legacy,
+    );
+
+    store.append(&user_id, &friends(), first.clone()).unwrap();
+    store.append(&user_id, &friends(), second.clone()).unwrap();
+    store.append(&user_id, &friends(), legacy.clone()).unwrap();
+
+    let result = store.load(&user_id, &friends());
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), vec![first, second, legacy]);
+}
+
+pub fn users_are_kept_separate(store: &dyn SeenEpisodesStore) {
+    store
+        .append(&UserID::new(1), &friends(), seen("s01e01"))
+        .unwrap();
+    store
+        .append(&UserID::new(2), &friends(), seen("s02e02"))
+        .unwrap();
+
+    assert_eq!(
+        episodes(store.load(&UserID::new(1), &friends()).unwrap()),
+        codes(&["s01e01"])
+    );
+    assert_eq!(
+        episodes(store.load(&UserID::new(2), &friends()).unwrap()),
+        codes(&["s02e02"])
+    );
+}
+
+pub fn list_returns_users_with_stored_data(store: &dyn SeenEpisodesStore) {
+    assert_eq!(store.list().unwrap(), Vec::new());
+
+    store
+        .append(&UserID::new(42), &friends(), seen("s01e01"))
+        .unwrap();
+    store
+        .append(&UserID::new(7), &friends(), seen("s01e01"))
+        .unwrap();
+    store
+        .append(&UserID::new(7), &friends(), seen("s01e02"))
+        .unwrap();
+
+    let result = store.list();
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), vec![UserID::new(7), UserID::new(42)]);
+}
+
+pub fn clear_removes_only_given_user(store: &dyn SeenEpisodesStore) {
+    store
+        .append(&UserID::new(1), &friends(), seen("s01e01"))
+        .unwrap();
+    store
+        .append(&UserID::new(2), &friends(), seen("s01e01"))
+        .unwrap();
+
+    let result = store.clear(&UserID::new(1), &friends());
+    assert!(result.is_ok(), "result is error: {result:#?}");
+
+    assert_eq!(store.load(&UserID::new(1), &friends()).unwrap(), Vec::new());
+    assert_eq!(
+        episodes(store.load(&UserID::new(2), &friends()).unwrap()),
+        codes(&["s01e01"])
+    );
+    assert_eq!(store.list().unwrap(), vec![UserID::new(2)]);
+}
+
+pub fn clear_does_not_fail_for_unknown_user(store: &dyn SeenEpisodesStore) {
+    let result = store.clear(&UserID::new(999), &friends());
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+}
+
+pub fn shows_are_kept_separate(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    store.append(&user_id, &office(), seen("s02e02")).unwrap();
+
+    assert_eq!(
+        episodes(store.load(&user_id, &friends()).unwrap()),
+        codes(&["s01e01"])
+    );
+    assert_eq!(
+        episodes(store.load(&user_id, &office()).unwrap()),
+        codes(&["s02e02"])
+    );
+}
+
+pub fn clear_removes_only_given_show(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    store.append(&user_id, &office(), seen("s01e01")).unwrap();
+
+    let result = store.clear(&user_id, &friends());
+    assert!(result.is_ok(), "result is error: {result:#?}");
+
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+    assert_eq!(
+        episodes(store.load(&user_id, &office()).unwrap()),
+        codes(&["s01e01"])
+    );
+    assert_eq!(store.list().unwrap(), vec![user_id]);
+}
+
+pub fn list_counts_user_with_several_shows_once(store: &dyn SeenEpisodesStore) {
+    store
+        .append(&UserID::new(7), &friends(), seen("s01e01"))
+        .unwrap();
+    store
+        .append(&UserID::new(7), &office(), seen("s01e01"))
+        .unwrap();
+
+    let result = store.list();
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), vec![UserID::new(7)]);
+}
+
+pub fn load_settings_returns_defaults_for_unknown_user(store: &dyn SeenEpisodesStore) {
+    let result = store.load_settings(&UserID::new(317));
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), UserSettings::default());
+}
+
+pub fn save_settings_overwrites_previous_settings(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    let settings = UserSettings {
+        show: Some(office()),
+        season_filters: [(friends(), SeasonFilter::new(1, 4).unwrap())].into(),
+        auto_reset: true,
+        show_keyboard: false,
+        ..UserSettings::default()
+    };
+
+    store
+        .save_settings(&user_id, &UserSettings::default())
+        .unwrap();
+    let result = store.save_settings(&user_id, &settings);
+    assert!(result.is_ok(), "result is error: {result:#?}");
+
+    assert_eq!(store.load_settings(&user_id).unwrap(), settings);
+    assert_eq!(
+        store.load_settings(&UserID::new(1)).unwrap(),
+        UserSettings::default()
+    );
+}
+
+pub fn archive_starts_new_cycle(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    assert_eq!(store.completed_cycles(&user_id, &friends()).unwrap(), 0);
+
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    let result = store.archive(&user_id, &friends());
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), 1);
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+
+    store.append(&user_id, &friends(), seen("s01e02")).unwrap();
+    assert_eq!(
+        episodes(store.load(&user_id, &friends()).unwrap()),
+        codes(&["s01e02"])
+    );
+    assert_eq!(store.archive(&user_id, &friends()).unwrap(), 2);
+    assert_eq!(store.completed_cycles(&user_id, &friends()).unwrap(), 2);
+}
+
+pub fn archive_skips_empty_cycle(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+
+    let result = store.archive(&user_id, &friends());
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), 0);
+    assert_eq!(store.completed_cycles(&user_id, &friends()).unwrap(), 0);
+}
+
+pub fn archive_completed_skips_incomplete_cycle(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    let all_episodes = codes(&["s01e01", "s01e02"]);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+
+    let result = store.archive_completed(&user_id, &friends(), &all_episodes);
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), None);
+    assert_eq!(store.completed_cycles(&user_id, &friends()).unwrap(), 0);
+    assert_eq!(
+        episodes(store.load(&user_id, &friends()).unwrap()),
+        codes(&["s01e01"])
+    );
+
+    store.append(&user_id, &friends(), seen("s01e02")).unwrap();
+    let result = store.archive_completed(&user_id, &friends(), &all_episodes);
+
+    assert_eq!(result.unwrap(), Some(1));
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+}
+
+pub fn archive_keeps_other_shows_and_users(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    store.append(&user_id, &office(), seen("s01e01")).unwrap();
+    store
+        .append(&UserID::new(1), &friends(), seen("s01e01"))
+        .unwrap();
+
+    store.archive(&user_id, &friends()).unwrap();
+
+    assert_eq!(store.load(&user_id, &office()).unwrap().len(), 1);
+    assert_eq!(store.load(&UserID::new(1), &friends()).unwrap().len(), 1);
+    assert_eq!(store.completed_cycles(&user_id, &office()).unwrap(), 0);
+    assert_eq!(
+        store.completed_cycles(&UserID::new(1), &friends()).unwrap(),
+        0
+    );
+}
+
+pub fn clear_keeps_completed_cycles(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    store.archive(&user_id, &friends()).unwrap();
+    store.append(&user_id, &friends(), seen("s01e02")).unwrap();
+
+    store.clear(&user_id, &friends()).unwrap();
+
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+    assert_eq!(store.completed_cycles(&user_id, &friends()).unwrap(), 1);
+}
+
+fn offered(code: &str, minutes: i64) -> OfferedEpisode {
+    let offered_at = DateTime::from_timestamp(1_700_000_000, 0).expect("valid timestamp");
+
+    OfferedEpisode::new(
+        Episode::try_from(code).unwrap(),
+        offered_at + TimeDelta::minutes(minutes),
+    )
+}
+
+pub fn load_recent_offers_returns_last_offers_in_order(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    assert_eq!(
+        store.load_recent_offers(&user_id, &friends(), 3).unwrap(),
+        Vec::new()
+    );
+
+    for (minutes, code) in ["s01e01", "s01e02", "s01e03", "s01e04"].iter().enumerate() {
+        let result = store.append_offer(&user_id, &friends(), offered(code, minutes as i64));
+        assert!(result.is_ok(), "result is error: {result:#?}");
+    }
+
+    let result = store.load_recent_offers(&user_id, &friends(), 3);
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(
+        result.unwrap(),
+        vec![
+            offered("s01e02", 1),
+            offered("s01e03", 2),
+            offered("s01e04", 3)
+        ]
+    );
+    assert_eq!(
+        store
+            .load_recent_offers(&user_id, &friends(), 10)
+            .unwrap()
+            .len(),
+        4
+    );
+}
+
+pub fn append_offer_keeps_only_retained_offers(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    for minutes in 0..OFFERS_RETENTION + 3 {
+        let result = store.append_offer(&user_id, &friends(), offered("s01e01", minutes as i64));
+        assert!(result.is_ok(), "result is error: {result:#?}");
+    }
+    store
+        .append_offer(&user_id, &office(), offered("s02e02", 0))
+        .unwrap();
+
+    let offers = store
+        .load_recent_offers(&user_id, &friends(), usize::MAX)
+        .unwrap();
+    assert_eq!(offers.len(), OFFERS_RETENTION);
+    assert_eq!(offers[0], offered("s01e01", 3));
+    assert_eq!(
+        offers.last(),
+        Some(&offered("s01e01", OFFERS_RETENTION as i64 + 2))
+    );
+    assert_eq!(
+        store
+            .load_recent_offers(&user_id, &office(), usize::MAX)
+            .unwrap(),
+        vec![offered("s02e02", 0)]
+    );
+}
+
+pub fn offers_are_kept_separate(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store
+        .append_offer(&user_id, &friends(), offered("s01e01", 0))
+        .unwrap();
+    store
+        .append_offer(&user_id, &office(), offered("s02e02", 0))
+        .unwrap();
+    store
+        .append_offer(&UserID::new(1), &friends(), offered("s03e03", 0))
+        .unwrap();
+
+    assert_eq!(
+        store.load_recent_offers(&user_id, &friends(), 5).unwrap(),
+        vec![offered("s01e01", 0)]
+    );
+    assert_eq!(
+        store.load_recent_offers(&user_id, &office(), 5).unwrap(),
+        vec![offered("s02e02", 0)]
+    );
+    // предложения не считаются историей просмотров
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+    assert_eq!(store.list().unwrap(), Vec::new());
+}
+
+pub fn remove_last_removes_latest_mark_of_episode(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    for code in ["s01e01", "s01e02", "s01e01", "s01e03"] {
+        store.append(&user_id, &friends(), seen(code)).unwrap();
+    }
+
+    let result = store.remove_last(
+        &user_id,
+        &friends(),
+        Some(&Episode::try_from("s01e01").unwrap()),
+    );
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), Some(seen("s01e01")));
+    assert_eq!(
+        episodes(store.load(&user_id, &friends()).unwrap()),
+        codes(&["s01e01", "s01e02", "s01e03"])
+    );
+    assert_eq!(
+        store
+            .remove_last(
+                &user_id,
+                &friends(),
+                Some(&Episode::try_from("s05e05").unwrap())
+            )
+            .unwrap(),
+        None
+    );
+}
+
+pub fn remove_last_without_episode_removes_latest_mark(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    store.append(&user_id, &friends(), seen("s01e02")).unwrap();
+    store.append(&user_id, &office(), seen("s02e02")).unwrap();
+
+    assert_eq!(
+        store.remove_last(&user_id, &friends(), None).unwrap(),
+        Some(seen("s01e02"))
+    );
+    assert_eq!(
+        store.remove_last(&user_id, &friends(), None).unwrap(),
+        Some(seen("s01e01"))
+    );
+
+    let result = store.remove_last(&user_id, &friends(), None);
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), None);
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+    assert_eq!(
+        episodes(store.load(&user_id, &office()).unwrap()),
+        codes(&["s02e02"])
+    );
+}
+
+pub fn remove_last_keeps_completed_cycles(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+    store.archive(&user_id, &friends()).unwrap();
+
+    let result = store.remove_last(&user_id, &friends(), None);
+
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    assert_eq!(result.unwrap(), None);
+    assert_eq!(store.completed_cycles(&user_id, &friends()).unwrap(), 1);
+}
+
+pub fn append_all_adds_marks_in_order(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    store.append(&user_id, &friends(), seen("s01e01")).unwrap();
+
+    let result = store.append_all(
+        &user_id,
+        &friends(),
+        vec![seen("s02e01"), seen("s02e02"), seen("s02e03")],
+    );
+    assert!(result.is_ok(), "result is error: {result:#?}");
+    store.append_all(&user_id, &friends(), Vec::new()).unwrap();
+
+    assert_eq!(
+        episodes(store.load(&user_id, &friends()).unwrap()),
+        codes(&["s01e01", "s02e01", "s02e02", "s02e03"])
+    );
+}
+
+pub fn remove_all_removes_every_mark_of_given_episodes(store: &dyn SeenEpisodesStore) {
+    let user_id = UserID::new(317);
+    for code in ["s01e01", "s01e02", "s01e01", "s01e03"] {
+        store.append(&user_id, &friends(), seen(code)).unwrap();
+    }
+    store.append(&user_id, &office(), seen("s01e01")).unwrap();
+
+    let result = store.remove_all(&user_id, &friends(), &codes(&["s01e01", "s01e03"]));
+    assert!(result.is_ok(), "result is error: {result:#?}");
+
+    assert_eq!(
+        episodes(store.load(&user_id, &friends()).unwrap()),
+        codes(&["s01e02"])
+    );
+    assert_eq!(store.load(&user_id, &office()).unwrap().len(), 1);
+
+    store
+        .remove_all(&user_id, &friends(), &codes(&["s01e02"]))
+        .unwrap();
+    assert_eq!(store.load(&user_id, &friends()).unwrap(), Vec::new());
+}
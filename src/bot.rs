@@ -1,12 +1,18 @@
 mod callback;
+mod seen_episodes_page;
+mod watch_buttons;
 
 use crate::{
-    application::{self, Application, Episode},
+    application::{
+        self, Application, Episode, EpisodeRanges, Language, SeasonFilter, SelectionStrategyKind,
+        Show, ShowID, UserSettings,
+    },
     error, watch_url_provider,
 };
 use std::{
     fmt::Display,
     sync::{Arc, OnceLock},
+    time::Duration,
 };
 use teloxide::{
     dispatching::UpdateHandler,
@@ -14,13 +20,22 @@ use teloxide::{
     prelude::*,
     requests::JsonRequest,
     sugar::bot::BotMessagesExt,
-    types::{InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup, User},
+    types::{
+        InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup, KeyboardRemove,
+        ReplyMarkup, User,
+    },
     utils::command::BotCommands,
 };
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type HandlerResult = Result<(), Error>;
-type WatchURLProvider = dyn watch_url_provider::WatchURLProvider + Send + Sync;
+type WatchProviders = watch_url_provider::WatchProviders;
+
+/// Приписка к сообщению с предложенной серией после нажатия «Посмотрел».
+const SEEN_MARK: &str = "\n\n✅ Просмотрено";
+
+/// Сколько ждать ссылки от сервисов для просмотра, прежде чем отправить предложение без них.
+const WATCH_URLS_DEADLINE: Duration = Duration::from_secs(2);
 
 #[derive(Clone, Copy)]
 enum MainKeyboardButtons {
@@ -47,8 +62,8 @@ impl Display for MainKeyboardButtons {
     }
 }
 
-/// Предлагаю для просмотра случайную серию сериала Друзья.
-/// Когда хочется посмотреть Друзей, но лень выбирать конкретную серию.
+/// Предлагаю для просмотра случайную серию сериала.
+/// Когда хочется посмотреть любимый сериал, но лень выбирать конкретную серию.
 ///
 /// Вот что я могу:
 #[derive(BotCommands, Clone)]
@@ -62,14 +77,26 @@ enum Command {
     NextEpisode,
     /// Показать список просмотренных серий.
     ListSeenEpisodes,
+    /// Отметить просмотренными: /mark s01e05, /mark s02e01-s02e10 или /mark s01.
+    Mark(String),
+    /// Снять отметку о просмотре: /unmark s01e05, /unmark s02e01-s02e10 или /unmark s01.
+    Unmark(String),
+    /// Отменить последнюю отметку о просмотре.
+    Undo,
     /// Очистить список просмотренных серий.
     ClearSeenEpisodes,
+    /// Выбрать сериал.
+    Shows,
+    /// Выбрать сезоны: /season 3, /season 1-4 или /season all.
+    Season(String),
+    /// Настройки.
+    Settings,
 }
 
 pub async fn new(
     bot_token: String,
     application: Arc<application::Application>,
-    watch_url_provider: Arc<WatchURLProvider>,
+    watch_providers: Arc<WatchProviders>,
 ) -> Dispatcher<Bot, Error, teloxide::dispatching::DefaultKey> {
     let bot = Bot::new(bot_token);
 
@@ -84,7 +111,7 @@ pub async fn new(
         .expect("не удалось установить список команд для бота");
 
     Dispatcher::builder(bot, build_handler())
-        .dependencies(dptree::deps![application, watch_url_provider])
+        .dependencies(dptree::deps![application, watch_providers])
         .default_handler(default_handler)
         .enable_ctrlc_handler()
         .build()
@@ -101,7 +128,13 @@ fn build_handler() -> UpdateHandler<Error> {
                 .branch(case![Command::Help].endpoint(help_handler))
                 .branch(case!(Command::NextEpisode).endpoint(next_episode_handler))
                 .branch(case!(Command::ListSeenEpisodes).endpoint(list_seen_episodes_handler))
-                .branch(case!(Command::ClearSeenEpisodes).endpoint(clear_seen_episodes_handler)),
+                .branch(case!(Command::Mark(episodes)).endpoint(mark_handler))
+                .branch(case!(Command::Unmark(episodes)).endpoint(unmark_handler))
+                .branch(case!(Command::Undo).endpoint(undo_handler))
+                .branch(case!(Command::ClearSeenEpisodes).endpoint(clear_seen_episodes_handler))
+                .branch(case!(Command::Shows).endpoint(shows_handler))
+                .branch(case!(Command::Season(seasons)).endpoint(season_handler))
+                .branch(case!(Command::Settings).endpoint(settings_handler)),
         )
         .branch(Update::filter_callback_query().endpoint(callback_handler))
         .branch(Update::filter_message().endpoint(message_handler))
@@ -119,13 +152,43 @@ async fn default_handler(upd: Arc<Update>) {
     tracing::warn!(update = upd_as_json, "Unhandled update");
 }
 
-fn build_main_keyboard() -> KeyboardMarkup {
+fn build_main_keyboard(settings: &UserSettings) -> ReplyMarkup {
+    if !settings.show_keyboard {
+        return ReplyMarkup::KeyboardRemove(KeyboardRemove::new());
+    }
+
     KeyboardMarkup::new(vec![
         vec![KeyboardButton::new(MainKeyboardButtons::Moar)],
         // vec![KeyboardButton::new(MainKeyboardButtons::ListSeenEpisodes)],
         // vec![KeyboardButton::new(MainKeyboardButtons::ClearSeenEpisodes)],
     ])
     .resize_keyboard()
+    .into()
+}
+
+/// Хранилище работает с файлами и SQLite синхронно, поэтому всё, что в него ходит,
+/// выполняется в пуле блокирующих потоков и не занимает потоки рантайма.
+async fn run_blocking<T, F>(application: &Arc<Application>, f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(Arc<Application>) -> T + Send + 'static,
+{
+    let application = Arc::clone(application);
+    match tokio::task::spawn_blocking(move || f(application)).await {
+        Ok(result) => result,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Настройки автора сообщения. У сообщений без автора настройки по умолчанию.
+fn load_user_settings(
+    application: &Application,
+    msg: &Message,
+) -> Result<UserSettings, application::Error> {
+    match &msg.from {
+        Some(user) => application.settings(application::UserID::new(user.id.0)),
+        None => Ok(UserSettings::default()),
+    }
 }
 
 fn log_endpoint_handling(user: Option<&User>, action: &str) {
@@ -159,18 +222,26 @@ fn log_endpoint_handling(user: Option<&User>, action: &str) {
     )
 }
 
-async fn start_handler(bot: Bot, msg: Message) -> HandlerResult {
+async fn start_handler(bot: Bot, msg: Message, application: Arc<Application>) -> HandlerResult {
     log_endpoint_handling(msg.from.as_ref(), "/start");
 
-    send_help_message(bot, msg).await?;
+    run_blocking(&application, move |application| {
+        send_help_message(bot, msg, application)
+    })
+    .await?
+    .await?;
 
     Ok(())
 }
 
-async fn help_handler(bot: Bot, msg: Message) -> HandlerResult {
+async fn help_handler(bot: Bot, msg: Message, application: Arc<Application>) -> HandlerResult {
     log_endpoint_handling(msg.from.as_ref(), "/help");
 
-    send_help_message(bot, msg).await?;
+    run_blocking(&application, move |application| {
+        send_help_message(bot, msg, application)
+    })
+    .await?
+    .await?;
 
     Ok(())
 }
@@ -179,11 +250,13 @@ async fn next_episode_handler(
     bot: Bot,
     msg: Message,
     application: Arc<Application>,
-    watch_url_provider: Arc<WatchURLProvider>,
+    watch_providers: Arc<WatchProviders>,
 ) -> HandlerResult {
     log_endpoint_handling(msg.from.as_ref(), "/next_episode");
 
-    send_next_episode_message(bot, msg, application, watch_url_provider)?.await?;
+    send_next_episode_message(bot, msg, application, watch_providers)
+        .await?
+        .await?;
 
     Ok(())
 }
@@ -192,6 +265,7 @@ async fn callback_handler(
     bot: Bot,
     q: CallbackQuery,
     application: Arc<Application>,
+    watch_providers: Arc<WatchProviders>,
 ) -> HandlerResult {
     let data = q.data.as_ref().map_or("", |s| s);
     log_endpoint_handling(Some(&q.from), &format!("callback with data {}", data));
@@ -216,10 +290,25 @@ async fn callback_handler(
 
     match command {
         callback::Command::MarkSeen(parameter) => {
-            handle_callback_mark_seen(bot, q, application, &parameter).await?
+            handle_callback_mark_seen(bot, q, application, parameter).await?
         }
-        callback::Command::ClearSeenEpisodes(option) => {
-            handle_callback_clear_seen_episodes(bot, q, application, option).await?
+        callback::Command::UnmarkSeen(parameter) => {
+            handle_callback_unmark_seen(bot, q, application, watch_providers, parameter).await?
+        }
+        callback::Command::ClearSeenEpisodes(parameter) => {
+            handle_callback_clear_seen_episodes(bot, q, application, parameter).await?
+        }
+        callback::Command::SelectShow(show_id) => {
+            handle_callback_select_show(bot, q, application, &show_id).await?
+        }
+        callback::Command::SetSeasonFilter(parameter) => {
+            handle_callback_season_filter(bot, q, application, parameter).await?
+        }
+        callback::Command::Settings(option) => {
+            handle_callback_settings(bot, q, application, watch_providers, option).await?
+        }
+        callback::Command::SeenEpisodesPage(parameter) => {
+            handle_callback_seen_episodes_page(bot, q, application, parameter).await?
         }
     }
 
@@ -230,12 +319,75 @@ async fn handle_callback_mark_seen(
     bot: Bot,
     q: CallbackQuery,
     application: Arc<Application>,
-    parameter: &str,
+    parameter: callback::MarkSeenParameter,
+) -> HandlerResult {
+    let episode = match application.find_episode(&parameter.show_id, &parameter.code) {
+        Ok(episode) => episode,
+        Err(err) => {
+            // устаревший или поддельный колбек, отмечать нечего
+            tracing::warn!(
+                error = err.to_string(),
+                "получили некорректную серию в колбеке mark_seen"
+            );
+            return Ok(());
+        }
+    };
+
+    let user_id = application::UserID::new(q.from.id.0);
+    let show_id = parameter.show_id.clone();
+    run_blocking(&application, move |application| {
+        application.mark_seen(user_id, &show_id, episode)
+    })
+    .await?;
+
+    let Some(message) = q.regular_message() else {
+        return Ok(());
+    };
+    let Some(text) = message.text() else {
+        return Ok(());
+    };
+
+    // ссылки на просмотр остаются, меняется только кнопка отметки
+    let keyboard = watch_buttons::swap_mark_button(
+        message.reply_markup(),
+        InlineKeyboardButton::callback(
+            "Отменить",
+            format!("unmark_seen={}:{}", parameter.show_id, parameter.code),
+        ),
+    );
+    bot.edit_text(message, format!("{text}{SEEN_MARK}"))
+        .reply_markup(keyboard)
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_callback_unmark_seen(
+    bot: Bot,
+    q: CallbackQuery,
+    application: Arc<Application>,
+    watch_providers: Arc<WatchProviders>,
+    parameter: callback::MarkSeenParameter,
 ) -> HandlerResult {
-    application.mark_seen(
-        application::UserID::new(q.from.id.0),
-        Episode::from(parameter),
-    )?;
+    let episode = match application.find_episode(&parameter.show_id, &parameter.code) {
+        Ok(episode) => episode,
+        Err(err) => {
+            tracing::warn!(
+                error = err.to_string(),
+                "получили некорректную серию в колбеке unmark_seen"
+            );
+            return Ok(());
+        }
+    };
+
+    let user_id = application::UserID::new(q.from.id.0);
+    let show_id = parameter.show_id.clone();
+    let unmarked_episode = episode.clone();
+    let settings = run_blocking(&application, move |application| {
+        application.unmark_seen(user_id, &show_id, &unmarked_episode)?;
+        application.settings(user_id)
+    })
+    .await?;
 
     let Some(message) = q.regular_message() else {
         return Ok(());
@@ -244,7 +396,18 @@ async fn handle_callback_mark_seen(
         return Ok(());
     };
 
-    bot.edit_text(message, format!("{text}\n\n✅ Просмотрено"))
+    // возвращаем сообщение с предложением в исходный вид, чтобы серию можно было отметить снова
+    let show = application.show(&parameter.show_id)?;
+    let keyboard = build_next_episode_keyboard(
+        &watch_providers,
+        &settings,
+        show,
+        &episode,
+        settings.season_filters.get(show.id()),
+    )
+    .await;
+    bot.edit_text(message, text.strip_suffix(SEEN_MARK).unwrap_or(text))
+        .reply_markup(keyboard)
         .await?;
 
     Ok(())
@@ -254,7 +417,7 @@ async fn handle_callback_clear_seen_episodes(
     bot: Bot,
     q: CallbackQuery,
     application: Arc<Application>,
-    option: callback::ClearSeenEpisodesOption,
+    parameter: callback::ClearSeenEpisodesParameter,
 ) -> HandlerResult {
     let Some(message) = q.regular_message() else {
         return Ok(());
@@ -263,7 +426,7 @@ async fn handle_callback_clear_seen_episodes(
         return Ok(());
     };
 
-    match option {
+    match parameter.option {
         callback::ClearSeenEpisodesOption::No => {
             bot.edit_text(
                 message,
@@ -274,8 +437,12 @@ async fn handle_callback_clear_seen_episodes(
             Ok(())
         }
         callback::ClearSeenEpisodesOption::Yes => {
-            let user = q.from.clone();
-            application.clear_seen_episodes(application::UserID::new(user.id.0))?;
+            let user_id = application::UserID::new(q.from.id.0);
+            let show_id = parameter.show_id.clone();
+            run_blocking(&application, move |application| {
+                application.clear_seen_episodes(user_id, &show_id)
+            })
+            .await?;
 
             bot.edit_text(
                 message,
@@ -288,30 +455,280 @@ async fn handle_callback_clear_seen_episodes(
     }
 }
 
+async fn handle_callback_seen_episodes_page(
+    bot: Bot,
+    q: CallbackQuery,
+    application: Arc<Application>,
+    parameter: callback::SeenEpisodesPageParameter,
+) -> HandlerResult {
+    let Some(message) = q.regular_message() else {
+        return Ok(());
+    };
+
+    let user_id = application::UserID::new(q.from.id.0);
+    let show = match application.show(&parameter.show_id) {
+        Ok(show) => show,
+        Err(err) => {
+            // сериал убрали из каталога после того, как отправили сообщение
+            tracing::warn!(
+                error = err.to_string(),
+                "получили неизвестный сериал в колбеке seen_page"
+            );
+            return Ok(());
+        }
+    };
+    let show_id = show.id().clone();
+    let (settings, seen_episodes) = run_blocking(&application, move |application| {
+        Ok::<_, application::Error>((
+            application.settings(user_id)?,
+            application.list_seen_episodes(user_id, &show_id)?,
+        ))
+    })
+    .await?;
+
+    let (text, keyboard) =
+        seen_episodes_page::build(show, &seen_episodes, parameter.page, settings.language);
+    let request = bot.edit_text(message, text);
+    match keyboard {
+        Some(keyboard) => request.reply_markup(keyboard).await?,
+        None => request.await?,
+    };
+
+    Ok(())
+}
+
+async fn handle_callback_select_show(
+    bot: Bot,
+    q: CallbackQuery,
+    application: Arc<Application>,
+    show_id: &ShowID,
+) -> HandlerResult {
+    let user_id = application::UserID::new(q.from.id.0);
+    let selected_show_id = show_id.clone();
+    let result = run_blocking(&application, move |application| {
+        application
+            .select_show(user_id, &selected_show_id)
+            .map(|show| show.name().to_string())
+    })
+    .await;
+    let show_name = match result {
+        Ok(show_name) => show_name,
+        Err(application::Error::UnknownShow(show_id)) => {
+            // сериал убрали из каталога после того, как отправили сообщение
+            tracing::warn!(
+                show_id = show_id,
+                "получили неизвестный сериал в колбеке select_show"
+            );
+            return Ok(());
+        }
+        Err(other) => return Err(other.into()),
+    };
+
+    let Some(message) = q.regular_message() else {
+        return Ok(());
+    };
+
+    bot.edit_text(message, format!("✅ Выбран сериал «{show_name}»."))
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_callback_season_filter(
+    bot: Bot,
+    q: CallbackQuery,
+    application: Arc<Application>,
+    parameter: callback::SeasonFilterParameter,
+) -> HandlerResult {
+    let user_id = application::UserID::new(q.from.id.0);
+    let show_id = parameter.show_id.clone();
+    let season_filter = parameter.season_filter;
+    let result = run_blocking(&application, move |application| {
+        application.set_season_filter(user_id, &show_id, season_filter)?;
+        application.settings(user_id)
+    })
+    .await;
+    let settings = match result {
+        Ok(settings) => settings,
+        Err(
+            err @ (application::Error::UnknownShow(_) | application::Error::SeasonFilterError(_)),
+        ) => {
+            // сериал или сезоны убрали из каталога после того, как отправили сообщение
+            tracing::warn!(
+                error = err.to_string(),
+                "получили некорректный фильтр в колбеке season_filter"
+            );
+            return Ok(());
+        }
+        Err(other) => return Err(other.into()),
+    };
+
+    let Some(message) = q.regular_message() else {
+        return Ok(());
+    };
+
+    bot.send_message(
+        message.chat.id,
+        format!(
+            "Теперь предлагаю серии {}.",
+            describe_season_filter(parameter.season_filter.as_ref())
+        ),
+    )
+    .reply_markup(build_main_keyboard(&settings))
+    .await?;
+
+    Ok(())
+}
+
+async fn handle_callback_settings(
+    bot: Bot,
+    q: CallbackQuery,
+    application: Arc<Application>,
+    watch_providers: Arc<WatchProviders>,
+    option: callback::SettingsOption,
+) -> HandlerResult {
+    let Some(message) = q.regular_message() else {
+        return Ok(());
+    };
+    let user_id = application::UserID::new(q.from.id.0);
+
+    let settings = match option {
+        callback::SettingsOption::Show => {
+            let active_show_id = run_blocking(&application, move |application| {
+                application
+                    .active_show(user_id)
+                    .map(|show| show.id().clone())
+            })
+            .await?;
+            let active_show = application.show(&active_show_id)?;
+            bot.send_message(message.chat.id, "Какой сериал будем смотреть?")
+                .reply_markup(build_shows_keyboard(&application, active_show))
+                .await?;
+
+            return Ok(());
+        }
+        callback::SettingsOption::Seasons => {
+            let (show_id, season_filter) = run_blocking(&application, move |application| {
+                let show = application.active_show(user_id)?;
+                let season_filter = application.season_filter(user_id, show.id())?;
+                Ok::<_, application::Error>((show.id().clone(), season_filter))
+            })
+            .await?;
+            let show = application.show(&show_id)?;
+            let (text, keyboard) = build_season_filter_picker(show, season_filter.as_ref());
+            bot.send_message(message.chat.id, text)
+                .reply_markup(keyboard)
+                .await?;
+
+            return Ok(());
+        }
+        callback::SettingsOption::Language => {
+            run_blocking(&application, move |application| {
+                application.update_settings(user_id, |settings| {
+                    settings.language = match settings.language {
+                        Language::Russian => Language::English,
+                        Language::English => Language::Russian,
+                    };
+                })
+            })
+            .await?
+        }
+        callback::SettingsOption::AutoReset => {
+            run_blocking(&application, move |application| {
+                application.update_settings(user_id, |settings| {
+                    settings.auto_reset = !settings.auto_reset;
+                })
+            })
+            .await?
+        }
+        callback::SettingsOption::WatchProvider => {
+            let watch_providers = Arc::clone(&watch_providers);
+            run_blocking(&application, move |application| {
+                application.update_settings(user_id, |settings| {
+                    let next = watch_providers.next_after(settings);
+                    settings.watch_provider = Some(next.id().to_string());
+                })
+            })
+            .await?
+        }
+        callback::SettingsOption::SelectionStrategy => {
+            run_blocking(&application, move |application| {
+                application.update_settings(user_id, |settings| {
+                    settings.selection_strategy = match settings.selection_strategy {
+                        SelectionStrategyKind::UniformUnseen => SelectionStrategyKind::LeastRecent,
+                        SelectionStrategyKind::LeastRecent => SelectionStrategyKind::Weighted,
+                        SelectionStrategyKind::Weighted => SelectionStrategyKind::UniformUnseen,
+                    };
+                })
+            })
+            .await?
+        }
+        callback::SettingsOption::ShowKeyboard => {
+            let settings = run_blocking(&application, move |application| {
+                application.update_settings(user_id, |settings| {
+                    settings.show_keyboard = !settings.show_keyboard;
+                })
+            })
+            .await?;
+
+            // клавиатура меняется только вместе с новым сообщением
+            let text = match settings.show_keyboard {
+                true => "Клавиатура включена.",
+                false => "Клавиатура скрыта. Следующую серию можно получить командой /next_episode",
+            };
+            bot.send_message(message.chat.id, text)
+                .reply_markup(build_main_keyboard(&settings))
+                .await?;
+
+            settings
+        }
+    };
+
+    let (text, keyboard) = run_blocking(&application, move |application| {
+        build_settings_menu(&application, &watch_providers, user_id, &settings)
+    })
+    .await?;
+    bot.edit_message_text(message.chat.id, message.id, text)
+        .reply_markup(keyboard)
+        .await?;
+
+    Ok(())
+}
+
 async fn message_handler(
     bot: Bot,
     msg: Message,
     application: Arc<Application>,
-    watch_url_provider: Arc<WatchURLProvider>,
+    watch_providers: Arc<WatchProviders>,
 ) -> HandlerResult {
     log_endpoint_handling(msg.from.as_ref(), "general_message");
 
     let text = match msg.text() {
         Some(text) => text,
         None => {
-            send_help_message(bot, msg).await?;
+            run_blocking(&application, move |application| {
+                send_help_message(bot, msg, application)
+            })
+            .await?
+            .await?;
             return Ok(());
         }
     };
 
     if text == MainKeyboardButtons::Moar.to_string() {
-        send_next_episode_message(bot, msg, application, watch_url_provider)?.await?;
+        send_next_episode_message(bot, msg, application, watch_providers)
+            .await?
+            .await?;
     // } else if text == MainKeyboardButtons::ListSeenEpisodes.to_string() {
     // send_seen_episodes(bot, msg, application)?.await?;
     // } else if text == MainKeyboardButtons::ClearSeenEpisodes.to_string() {
     // send_clear_seen_episodes_confirmation_request(bot, msg)?.await?;
     } else {
-        send_help_message(bot, msg).await?;
+        run_blocking(&application, move |application| {
+            send_help_message(bot, msg, application)
+        })
+        .await?
+        .await?;
     };
 
     Ok(())
@@ -324,7 +741,11 @@ async fn list_seen_episodes_handler(
 ) -> HandlerResult {
     log_endpoint_handling(msg.from.as_ref(), "/list_seen_episodes");
 
-    send_seen_episodes(bot, msg, application)?.await?;
+    run_blocking(&application, move |application| {
+        send_seen_episodes(bot, msg, application)
+    })
+    .await?
+    .await?;
 
     Ok(())
 }
@@ -336,30 +757,165 @@ async fn clear_seen_episodes_handler(
 ) -> HandlerResult {
     log_endpoint_handling(msg.from.as_ref(), "/clear_seen_episodes");
 
-    send_clear_seen_episodes_confirmation_request(bot, msg, application)?.await?;
+    run_blocking(&application, move |application| {
+        send_clear_seen_episodes_confirmation_request(bot, msg, application)
+    })
+    .await?
+    .await?;
 
     Ok(())
 }
 
-fn send_help_message(bot: Bot, msg: Message) -> JsonRequest<SendMessage> {
-    bot.send_message(msg.chat.id, Command::descriptions().to_string())
-        .reply_markup(build_main_keyboard())
+async fn mark_handler(
+    bot: Bot,
+    msg: Message,
+    episodes: String,
+    application: Arc<Application>,
+) -> HandlerResult {
+    log_endpoint_handling(msg.from.as_ref(), "/mark");
+
+    run_blocking(&application, move |application| {
+        send_manual_mark_message(bot, msg, application, episodes.trim(), true)
+    })
+    .await?
+    .await?;
+
+    Ok(())
 }
 
-fn send_next_episode_message(
+async fn unmark_handler(
+    bot: Bot,
+    msg: Message,
+    episodes: String,
+    application: Arc<Application>,
+) -> HandlerResult {
+    log_endpoint_handling(msg.from.as_ref(), "/unmark");
+
+    run_blocking(&application, move |application| {
+        send_manual_mark_message(bot, msg, application, episodes.trim(), false)
+    })
+    .await?
+    .await?;
+
+    Ok(())
+}
+
+async fn undo_handler(bot: Bot, msg: Message, application: Arc<Application>) -> HandlerResult {
+    log_endpoint_handling(msg.from.as_ref(), "/undo");
+
+    run_blocking(&application, move |application| {
+        send_undo_message(bot, msg, application)
+    })
+    .await?
+    .await?;
+
+    Ok(())
+}
+
+async fn shows_handler(bot: Bot, msg: Message, application: Arc<Application>) -> HandlerResult {
+    log_endpoint_handling(msg.from.as_ref(), "/shows");
+
+    run_blocking(&application, move |application| {
+        send_shows(bot, msg, application)
+    })
+    .await?
+    .await?;
+
+    Ok(())
+}
+
+async fn season_handler(
+    bot: Bot,
+    msg: Message,
+    seasons: String,
+    application: Arc<Application>,
+) -> HandlerResult {
+    log_endpoint_handling(msg.from.as_ref(), "/season");
+
+    run_blocking(&application, move |application| {
+        send_season_filter_message(bot, msg, application, seasons.trim())
+    })
+    .await?
+    .await?;
+
+    Ok(())
+}
+
+async fn settings_handler(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+    watch_providers: Arc<WatchProviders>,
+) -> HandlerResult {
+    log_endpoint_handling(msg.from.as_ref(), "/settings");
+
+    run_blocking(&application, move |application| {
+        send_settings(bot, msg, application, watch_providers)
+    })
+    .await?
+    .await?;
+
+    Ok(())
+}
+
+fn send_help_message(
     bot: Bot,
     msg: Message,
     application: Arc<Application>,
-    watch_url_provider: Arc<WatchURLProvider>,
+) -> Result<JsonRequest<SendMessage>, application::Error> {
+    let settings = load_user_settings(&application, &msg)?;
+
+    Ok(bot
+        .send_message(msg.chat.id, Command::descriptions().to_string())
+        .reply_markup(build_main_keyboard(&settings)))
+}
+
+async fn send_next_episode_message(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+    watch_providers: Arc<WatchProviders>,
 ) -> Result<JsonRequest<SendMessage>, application::Error> {
     let user = msg.from.expect("should not be None at this point");
 
-    let next_episode = match application.get_next_episode(application::UserID::new(user.id.0)) {
+    let user_id = application::UserID::new(user.id.0);
+    let (show_id, settings, next_episode, completed_cycles) =
+        run_blocking(&application, move |application| {
+            let show = application.active_show(user_id)?;
+            let settings = application.settings(user_id)?;
+            let next_episode = application.get_next_episode(user_id, show.id());
+            let completed_cycles = match next_episode {
+                Ok(_) => application.completed_cycles(user_id, show.id())?,
+                Err(_) => 0,
+            };
+            Ok::<_, application::Error>((
+                show.id().clone(),
+                settings,
+                next_episode,
+                completed_cycles,
+            ))
+        })
+        .await?;
+    let show = application.show(&show_id)?;
+    let season_filter = settings.season_filters.get(show.id());
+
+    let next_episode = match next_episode {
         Ok(next_episode) => next_episode,
+        Err(application::Error::NoUnseenEpisodes) if season_filter.is_some() => {
+            return Ok(bot
+                .send_message(
+                    msg.chat.id,
+                    "В выбранных сезонах не осталось непросмотренных серий 🙂\n\nВыбрать другие сезоны: /season",
+                )
+                .reply_markup(build_main_keyboard(&settings)));
+        }
         Err(application::Error::NoUnseenEpisodes) => {
             return Ok(bot
-                .send_message(msg.chat.id, "Не осталось непросмотренных серий 🙂")
-                .reply_markup(build_main_keyboard()));
+                .send_message(
+                    msg.chat.id,
+                    "Не осталось непросмотренных серий 🙂\n\nЧтобы начинать новый круг и не терять историю, включите бесконечный просмотр в /settings",
+                )
+                .reply_markup(build_main_keyboard(&settings)));
         }
         Err(other) => {
             tracing::error!(
@@ -370,37 +926,244 @@ fn send_next_episode_message(
         }
     };
 
-    let watch_url = watch_url_provider.build_url(&next_episode);
+    // название сериала нужно, только если есть из чего выбирать
+    let show_name = match application.shows().len() {
+        1 => String::new(),
+        _ => format!("{}\n", show.name()),
+    };
+    let cycle = match completed_cycles {
+        0 => String::new(),
+        completed_cycles => format!("🔁 Круг {}\n", completed_cycles + 1),
+    };
 
     let response = format!(
         r#"
 Предлагаю посмотреть:
 
-Сезон {} серия {}
-
-{watch_url}
+{show_name}{cycle}{}
 "#,
-        next_episode.season(),
-        next_episode.episode(),
+        describe_episode(&next_episode, settings.language),
     );
 
-    let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
-        "Посмотрел",
-        format!("mark_seen={}", next_episode.code()),
-    )]]);
+    let keyboard = build_next_episode_keyboard(
+        &watch_providers,
+        &settings,
+        show,
+        &next_episode,
+        season_filter,
+    )
+    .await;
 
     Ok(bot
         .send_message(msg.chat.id, response.trim())
         .reply_markup(keyboard))
 }
 
+async fn build_next_episode_keyboard(
+    watch_providers: &WatchProviders,
+    settings: &UserSettings,
+    show: &Show,
+    episode: &Episode,
+    season_filter: Option<&SeasonFilter>,
+) -> InlineKeyboardMarkup {
+    let show_id = show.id();
+
+    // по кнопке на каждый сервис, выбранный пользователем первым; без шаблона ссылки кнопки нет
+    let urls = watch_providers
+        .build_urls(settings, show, episode, WATCH_URLS_DEADLINE)
+        .await;
+    let mut links = Vec::new();
+    for (provider, url) in urls {
+        if !url.trim().is_empty() && !provider.is_healthy() {
+            // зеркал нет или они тоже лежат: ссылка лучше, чем никакой, но об этом стоит знать
+            tracing::warn!(
+                provider = provider.id(),
+                url = url,
+                "показываем ссылку на сервис, который не проходит проверки"
+            );
+        }
+        links.push(watch_buttons::WatchLink {
+            provider_id: provider.id(),
+            label: provider.label(),
+            url,
+        });
+    }
+    let mut keyboard = watch_buttons::build(links, watch_providers.list().len() == 1);
+
+    let mut season_buttons = vec![InlineKeyboardButton::callback(
+        format!("Только {} сезон", episode.season()),
+        format!("season_filter={show_id}:{}", episode.season()),
+    )];
+    if season_filter.is_some() {
+        season_buttons.push(InlineKeyboardButton::callback(
+            "Все сезоны",
+            format!("season_filter={show_id}:all"),
+        ));
+    }
+
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "Посмотрел",
+        format!("mark_seen={show_id}:{}", episode.code()),
+    )]);
+    keyboard.push(season_buttons);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Отмечает серии просмотренными, если `seen`, или снимает с них отметку.
+fn send_manual_mark_message(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+    episodes: &str,
+    seen: bool,
+) -> Result<JsonRequest<SendMessage>, application::Error> {
+    let user = msg.from.expect("should not be None at this point");
+    let user_id = application::UserID::new(user.id.0);
+    let settings = application.settings(user_id)?;
+    let show = application.active_show(user_id)?;
+    let command = if seen { "/mark" } else { "/unmark" };
+
+    let text = match episodes.parse::<EpisodeRanges>() {
+        Err(_) => format!(
+            "Не понял, какие серии {}. Примеры: {command} s01e05, {command} s02e01-s02e10 или {command} s01 для целого сезона.",
+            if seen {
+                "отметить"
+            } else {
+                "снять с отметки"
+            },
+        ),
+        Ok(episode_ranges) => {
+            let result = if seen {
+                application.mark_seen_episodes(user_id, show.id(), &episode_ranges)
+            } else {
+                application.unmark_seen_episodes(user_id, show.id(), &episode_ranges)
+            };
+
+            match result {
+                Ok(episodes) if episodes.is_empty() && seen => {
+                    String::from("Все эти серии уже отмечены просмотренными.")
+                }
+                Ok(episodes) if episodes.is_empty() => {
+                    String::from("Ни одна из этих серий не отмечена просмотренной.")
+                }
+                Ok(episodes) if seen => {
+                    format!("✅ Отмечено просмотренными серий: {}", episodes.len())
+                }
+                Ok(episodes) => format!("↩️ Снята отметка с серий: {}", episodes.len()),
+                Err(application::Error::UnknownEpisode(code)) => {
+                    format!("В сериале «{}» нет серии {code}.", show.name())
+                }
+                Err(application::Error::EpisodeRangeError(_)) => {
+                    format!("В сериале «{}» нет таких сезонов.", show.name())
+                }
+                Err(other) => return Err(other),
+            }
+        }
+    };
+
+    Ok(bot
+        .send_message(msg.chat.id, text)
+        .reply_markup(build_main_keyboard(&settings)))
+}
+
+fn send_undo_message(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+) -> Result<JsonRequest<SendMessage>, application::Error> {
+    let settings = load_user_settings(&application, &msg)?;
+    let user = msg.from.expect("should not be None at this point");
+    let user_id = application::UserID::new(user.id.0);
+    let show = application.active_show(user_id)?;
+
+    let text = match application.undo_last_mark(user_id, show.id())? {
+        Some(episode) => format!(
+            "↩️ Отметка о просмотре снята: сезон {} серия {}",
+            episode.season(),
+            episode.episode()
+        ),
+        None => String::from("В текущем круге нет отметок о просмотре, отменять нечего."),
+    };
+
+    Ok(bot
+        .send_message(msg.chat.id, text)
+        .reply_markup(build_main_keyboard(&settings)))
+}
+
+fn describe_season_filter(season_filter: Option<&SeasonFilter>) -> String {
+    match season_filter {
+        None => String::from("из всех сезонов"),
+        Some(filter) if filter.first() == filter.last() => {
+            format!("только из {} сезона", filter.first())
+        }
+        Some(filter) => format!("только из сезонов {}–{}", filter.first(), filter.last()),
+    }
+}
+
+fn describe_episode(episode: &Episode, language: Language) -> String {
+    let mut lines = vec![format!(
+        "Сезон {} серия {}",
+        episode.season(),
+        episode.episode()
+    )];
+
+    let Some(metadata) = episode.metadata() else {
+        return lines.join("\n");
+    };
+
+    match language {
+        Language::Russian => {
+            if let Some(localized_title) = &metadata.localized_title {
+                lines.push(format!("«{localized_title}»"));
+            }
+            if let Some(title) = &metadata.title {
+                lines.push(title.clone());
+            }
+        }
+        Language::English => {
+            // если оригинального названия в каталоге нет, лучше показать перевод, чем ничего
+            if let Some(title) = metadata
+                .title
+                .as_ref()
+                .or(metadata.localized_title.as_ref())
+            {
+                lines.push(title.clone());
+            }
+        }
+    }
+
+    let details: Vec<String> = [
+        metadata
+            .air_date
+            .map(|air_date| format!("вышла {}", air_date.format("%d.%m.%Y"))),
+        metadata.runtime.map(|runtime| format!("{runtime} мин")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !details.is_empty() {
+        lines.push(details.join(" · "));
+    }
+
+    if let Some(synopsis) = &metadata.synopsis {
+        lines.push(String::new());
+        lines.push(synopsis.clone());
+    }
+
+    lines.join("\n")
+}
+
 fn send_seen_episodes(
     bot: Bot,
     msg: Message,
     application: Arc<Application>,
 ) -> Result<JsonRequest<SendMessage>, application::Error> {
     let user = msg.from.expect("should not be None at this point");
-    let seen_episodes = application.list_seen_episodes(application::UserID::new(user.id.0))?;
+    let user_id = application::UserID::new(user.id.0);
+    let settings = application.settings(user_id)?;
+    let show = application.active_show(user_id)?;
+    let seen_episodes = application.list_seen_episodes(user_id, show.id())?;
 
     if seen_episodes.is_empty() {
         let text = r#"
@@ -411,29 +1174,16 @@ fn send_seen_episodes(
 
         return Ok(bot
             .send_message(msg.chat.id, text.trim())
-            .reply_markup(build_main_keyboard()));
+            .reply_markup(build_main_keyboard(&settings)));
     }
 
-    fn episode_to_string(episode: &Episode) -> String {
-        format!("Сезон {} серия {}", episode.season(), episode.episode())
-    }
+    let (text, keyboard) = seen_episodes_page::build(show, &seen_episodes, 0, settings.language);
 
-    let text = format!(
-        r#"
-Просмотренные серии. Наверху недавние, внизу старые:
-
-{}
-"#,
-        seen_episodes.iter().fold(String::new(), |acc, ep| format!(
-            "{}\n{}",
-            episode_to_string(ep),
-            acc,
-        )),
-    );
-
-    Ok(bot
-        .send_message(msg.chat.id, text.trim())
-        .reply_markup(build_main_keyboard()))
+    let request = bot.send_message(msg.chat.id, text);
+    Ok(match keyboard {
+        Some(keyboard) => request.reply_markup(keyboard),
+        None => request.reply_markup(build_main_keyboard(&settings)),
+    })
 }
 
 fn send_clear_seen_episodes_confirmation_request(
@@ -442,7 +1192,10 @@ fn send_clear_seen_episodes_confirmation_request(
     application: Arc<Application>,
 ) -> Result<JsonRequest<SendMessage>, application::Error> {
     let user = msg.from.expect("should not be None at this point");
-    let seen_episodes = application.list_seen_episodes(application::UserID::new(user.id.0))?;
+    let user_id = application::UserID::new(user.id.0);
+    let settings = application.settings(user_id)?;
+    let show = application.active_show(user_id)?;
+    let seen_episodes = application.list_seen_episodes(user_id, show.id())?;
 
     if seen_episodes.is_empty() {
         return Ok(bot
@@ -450,18 +1203,247 @@ fn send_clear_seen_episodes_confirmation_request(
                 msg.chat.id,
                 "Нечего очищать, список просмотренных серий пуст.",
             )
-            .reply_markup(build_main_keyboard()));
+            .reply_markup(build_main_keyboard(&settings)));
     }
 
     let keyboard = InlineKeyboardMarkup::new(vec![vec![
-        InlineKeyboardButton::callback("Да", "clear_seen_episodes=yes"),
-        InlineKeyboardButton::callback("Нет", "clear_seen_episodes=no"),
+        InlineKeyboardButton::callback("Да", format!("clear_seen_episodes=yes:{}", show.id())),
+        InlineKeyboardButton::callback("Нет", format!("clear_seen_episodes=no:{}", show.id())),
     ]]);
 
     Ok(bot
         .send_message(
             msg.chat.id,
-            "Вы точно хотите очистить список просмотренных серий?",
+            format!(
+                "Вы точно хотите очистить список просмотренных серий сериала «{}»?",
+                show.name()
+            ),
         )
         .reply_markup(keyboard))
 }
+
+fn send_shows(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+) -> Result<JsonRequest<SendMessage>, application::Error> {
+    let user = msg.from.expect("should not be None at this point");
+    let active_show = application.active_show(application::UserID::new(user.id.0))?;
+
+    Ok(bot
+        .send_message(msg.chat.id, "Какой сериал будем смотреть?")
+        .reply_markup(build_shows_keyboard(&application, active_show)))
+}
+
+fn build_shows_keyboard(application: &Application, active_show: &Show) -> InlineKeyboardMarkup {
+    fn show_to_button(show: &Show, is_active: bool) -> Vec<InlineKeyboardButton> {
+        let mark = if is_active { "✅ " } else { "" };
+
+        vec![InlineKeyboardButton::callback(
+            format!("{mark}{}", show.name()),
+            format!("select_show={}", show.id()),
+        )]
+    }
+
+    InlineKeyboardMarkup::new(
+        application
+            .shows()
+            .iter()
+            .map(|show| show_to_button(show, show.id() == active_show.id())),
+    )
+}
+
+fn send_season_filter_message(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+    seasons: &str,
+) -> Result<JsonRequest<SendMessage>, application::Error> {
+    let user = msg.from.expect("should not be None at this point");
+    let user_id = application::UserID::new(user.id.0);
+    let settings = application.settings(user_id)?;
+    let show = application.active_show(user_id)?;
+
+    if seasons.is_empty() {
+        let season_filter = settings.season_filters.get(show.id());
+        let (text, keyboard) = build_season_filter_picker(show, season_filter);
+
+        return Ok(bot.send_message(msg.chat.id, text).reply_markup(keyboard));
+    }
+
+    let season_filter = match seasons {
+        "all" => None,
+        seasons => match seasons.parse::<SeasonFilter>() {
+            Ok(season_filter) => Some(season_filter),
+            Err(_) => {
+                return Ok(bot
+                    .send_message(
+                        msg.chat.id,
+                        "Не понял, какие сезоны выбрать. Примеры: /season 3, /season 1-4 или /season all.",
+                    )
+                    .reply_markup(build_main_keyboard(&settings)));
+            }
+        },
+    };
+
+    let text = match application.set_season_filter(user_id, show.id(), season_filter) {
+        Ok(()) => format!(
+            "Теперь предлагаю серии {}.",
+            describe_season_filter(season_filter.as_ref())
+        ),
+        Err(application::Error::SeasonFilterError(_)) => {
+            format!("В сериале «{}» нет таких сезонов.", show.name())
+        }
+        Err(other) => return Err(other),
+    };
+
+    Ok(bot
+        .send_message(msg.chat.id, text)
+        .reply_markup(build_main_keyboard(&settings)))
+}
+
+fn build_season_filter_picker(
+    show: &Show,
+    season_filter: Option<&SeasonFilter>,
+) -> (String, InlineKeyboardMarkup) {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = show
+        .seasons()
+        .chunks(5)
+        .map(|seasons| {
+            seasons
+                .iter()
+                .map(|season| {
+                    InlineKeyboardButton::callback(
+                        season.to_string(),
+                        format!("season_filter={}:{season}", show.id()),
+                    )
+                })
+                .collect()
+        })
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "Все сезоны",
+        format!("season_filter={}:all", show.id()),
+    )]);
+
+    let text = format!(
+        r#"
+Сейчас предлагаю серии {}.
+
+Выберите сезон или отправьте диапазон командой, например, /season 1-4.
+"#,
+        describe_season_filter(season_filter),
+    );
+
+    (text.trim().to_string(), InlineKeyboardMarkup::new(keyboard))
+}
+
+fn send_settings(
+    bot: Bot,
+    msg: Message,
+    application: Arc<Application>,
+    watch_providers: Arc<WatchProviders>,
+) -> Result<JsonRequest<SendMessage>, application::Error> {
+    let user = msg.from.expect("should not be None at this point");
+    let user_id = application::UserID::new(user.id.0);
+    let settings = application.settings(user_id)?;
+
+    let (text, keyboard) = build_settings_menu(&application, &watch_providers, user_id, &settings)?;
+
+    Ok(bot.send_message(msg.chat.id, text).reply_markup(keyboard))
+}
+
+fn build_settings_menu(
+    application: &Application,
+    watch_providers: &WatchProviders,
+    user_id: application::UserID,
+    settings: &UserSettings,
+) -> Result<(String, InlineKeyboardMarkup), application::Error> {
+    let show = application.active_show(user_id)?;
+    let seasons = match settings.season_filters.get(show.id()) {
+        None => String::from("все"),
+        Some(filter) if filter.first() == filter.last() => filter.first().to_string(),
+        Some(filter) => format!("{}–{}", filter.first(), filter.last()),
+    };
+    let language = match settings.language {
+        Language::Russian => "на русском",
+        Language::English => "в оригинале",
+    };
+    let auto_reset = if settings.auto_reset {
+        "да"
+    } else {
+        "нет"
+    };
+    let selection_strategy = match settings.selection_strategy {
+        SelectionStrategyKind::UniformUnseen => "только новые",
+        SelectionStrategyKind::LeastRecent => "давно не виденные",
+        SelectionStrategyKind::Weighted => "чаще давние",
+    };
+    let show_keyboard = if settings.show_keyboard {
+        "показывать"
+    } else {
+        "не показывать"
+    };
+
+    let text = format!(
+        r#"
+Настройки
+
+Сериал: {}
+Сезоны: {seasons}
+Названия серий: {language}
+Ссылки для просмотра: {}
+Бесконечный просмотр: {auto_reset}{}
+Выбор серий: {selection_strategy}
+Клавиатура «{}»: {show_keyboard}
+"#,
+        show.name(),
+        watch_providers.default_for(settings).label(),
+        match application.completed_cycles(user_id, show.id())? {
+            0 => String::new(),
+            completed_cycles => format!(", пройдено кругов: {completed_cycles}"),
+        },
+        MainKeyboardButtons::Moar,
+    );
+
+    let mut keyboard = Vec::new();
+    // выбирать сериал имеет смысл, только если их несколько
+    if application.shows().len() > 1 {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            format!("Сериал: {}", show.name()),
+            "settings=show",
+        )]);
+    }
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        format!("Сезоны: {seasons}"),
+        "settings=seasons",
+    )]);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        format!("Названия: {language}"),
+        "settings=language",
+    )]);
+    // выбирать сервис имеет смысл, только если их несколько
+    if watch_providers.list().len() > 1 {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            format!(
+                "Смотреть в: {}",
+                watch_providers.default_for(settings).label()
+            ),
+            "settings=watch_provider",
+        )]);
+    }
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        format!("Бесконечный просмотр: {auto_reset}"),
+        "settings=auto_reset",
+    )]);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        format!("Выбор серий: {selection_strategy}"),
+        "settings=selection_strategy",
+    )]);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        format!("Клавиатура: {show_keyboard}"),
+        "settings=show_keyboard",
+    )]);
+
+    Ok((text.trim().to_string(), InlineKeyboardMarkup::new(keyboard)))
+}
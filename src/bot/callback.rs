@@ -1,16 +1,29 @@
 use super::error::Error;
+use crate::application::{SeasonFilter, ShowID};
 
 pub enum Command {
-    MarkSeen(String),
-    ClearSeenEpisodes(ClearSeenEpisodesOption),
+    MarkSeen(MarkSeenParameter),
+    UnmarkSeen(MarkSeenParameter),
+    ClearSeenEpisodes(ClearSeenEpisodesParameter),
+    SelectShow(ShowID),
+    SetSeasonFilter(SeasonFilterParameter),
+    Settings(SettingsOption),
+    SeenEpisodesPage(SeenEpisodesPageParameter),
 }
 
 impl Command {
     fn from(command: &str, parameter: &str) -> Result<Command, Error> {
         match command {
-            "mark_seen" => Ok(Command::MarkSeen(parameter.to_string())),
+            "mark_seen" => Ok(Command::MarkSeen(MarkSeenParameter::from(parameter))),
+            "unmark_seen" => Ok(Command::UnmarkSeen(MarkSeenParameter::from(parameter))),
             "clear_seen_episodes" => {
-                ClearSeenEpisodesOption::from(parameter).map(Command::ClearSeenEpisodes)
+                ClearSeenEpisodesParameter::from(parameter).map(Command::ClearSeenEpisodes)
+            }
+            "select_show" => Ok(Command::SelectShow(ShowID::new(parameter))),
+            "season_filter" => SeasonFilterParameter::from(parameter).map(Command::SetSeasonFilter),
+            "settings" => SettingsOption::from(parameter).map(Command::Settings),
+            "seen_page" => {
+                SeenEpisodesPageParameter::from(parameter).map(Command::SeenEpisodesPage)
             }
             _ => Err(Error::CallbackCommandParseError(format!(
                 "неопознанная команда: command={command}"
@@ -32,6 +45,102 @@ impl Command {
     }
 }
 
+/// Параметр вида `<show_id>:<code>`. В колбеках, отправленных до поддержки нескольких
+/// сериалов, был только код серии, они относятся к сериалу по умолчанию.
+pub struct MarkSeenParameter {
+    pub show_id: ShowID,
+    pub code: String,
+}
+
+impl MarkSeenParameter {
+    fn from(parameter: &str) -> MarkSeenParameter {
+        let (show_id, code) = match parameter.split_once(':') {
+            Some((show_id, code)) => (ShowID::new(show_id), code),
+            None => (ShowID::default(), parameter),
+        };
+
+        MarkSeenParameter {
+            show_id,
+            code: code.to_string(),
+        }
+    }
+}
+
+/// Параметр вида `<option>:<show_id>`, сериал можно не указывать по той же причине,
+/// что и в [`MarkSeenParameter`].
+pub struct ClearSeenEpisodesParameter {
+    pub option: ClearSeenEpisodesOption,
+    pub show_id: ShowID,
+}
+
+impl ClearSeenEpisodesParameter {
+    fn from(parameter: &str) -> Result<ClearSeenEpisodesParameter, Error> {
+        let (option, show_id) = match parameter.split_once(':') {
+            Some((option, show_id)) => (option, ShowID::new(show_id)),
+            None => (parameter, ShowID::default()),
+        };
+
+        Ok(ClearSeenEpisodesParameter {
+            option: ClearSeenEpisodesOption::from(option)?,
+            show_id,
+        })
+    }
+}
+
+/// Параметр вида `<show_id>:<seasons>`, где `seasons` это номер сезона, диапазон `N-M`
+/// или `all`, чтобы снять ограничение.
+pub struct SeasonFilterParameter {
+    pub show_id: ShowID,
+    pub season_filter: Option<SeasonFilter>,
+}
+
+impl SeasonFilterParameter {
+    fn from(parameter: &str) -> Result<SeasonFilterParameter, Error> {
+        let Some((show_id, seasons)) = parameter.split_once(':') else {
+            return Err(Error::CallbackCommandParseError(format!(
+                "ожидали сериал и сезоны для команды SeasonFilter: parameter={parameter}"
+            )));
+        };
+
+        let season_filter = match seasons {
+            "all" => None,
+            seasons => Some(seasons.parse().map_err(|err| {
+                Error::CallbackCommandParseError(format!(
+                    "неопознанные сезоны для команды SeasonFilter: {err}"
+                ))
+            })?),
+        };
+
+        Ok(SeasonFilterParameter {
+            show_id: ShowID::new(show_id),
+            season_filter,
+        })
+    }
+}
+
+/// Параметр вида `<show_id>:<page>`, страницы нумеруются с нуля.
+pub struct SeenEpisodesPageParameter {
+    pub show_id: ShowID,
+    pub page: usize,
+}
+
+impl SeenEpisodesPageParameter {
+    fn from(parameter: &str) -> Result<SeenEpisodesPageParameter, Error> {
+        let parse_error = || {
+            Error::CallbackCommandParseError(format!(
+                "ожидали сериал и номер страницы для команды SeenEpisodesPage: parameter={parameter}"
+            ))
+        };
+
+        let (show_id, page) = parameter.split_once(':').ok_or_else(parse_error)?;
+
+        Ok(SeenEpisodesPageParameter {
+            show_id: ShowID::new(show_id),
+            page: page.parse().map_err(|_| parse_error())?,
+        })
+    }
+}
+
 pub enum ClearSeenEpisodesOption {
     No,
     Yes,
@@ -48,3 +157,31 @@ impl ClearSeenEpisodesOption {
         }
     }
 }
+
+/// Кнопки меню настроек.
+pub enum SettingsOption {
+    Show,
+    Seasons,
+    Language,
+    AutoReset,
+    WatchProvider,
+    SelectionStrategy,
+    ShowKeyboard,
+}
+
+impl SettingsOption {
+    fn from(option: &str) -> Result<SettingsOption, Error> {
+        match option {
+            "show" => Ok(SettingsOption::Show),
+            "seasons" => Ok(SettingsOption::Seasons),
+            "language" => Ok(SettingsOption::Language),
+            "auto_reset" => Ok(SettingsOption::AutoReset),
+            "watch_provider" => Ok(SettingsOption::WatchProvider),
+            "selection_strategy" => Ok(SettingsOption::SelectionStrategy),
+            "show_keyboard" => Ok(SettingsOption::ShowKeyboard),
+            _ => Err(Error::CallbackCommandParseError(format!(
+                "неопознанный вариант для команды Settings: option={option}"
+            ))),
+        }
+    }
+}
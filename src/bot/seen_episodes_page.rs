@@ -0,0 +1,172 @@
+//! Список просмотренных серий по страницам, чтобы сообщение не упиралось в лимит
+//! Telegram в 4096 символов.
+
+use crate::application::{Episode, Language, SeenEpisode, Show};
+use std::collections::{BTreeMap, HashSet};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+/// Сколько серий показывать на одной странице. Даже с длинными названиями
+/// страница остаётся заметно короче лимита Telegram.
+pub const PAGE_SIZE: usize = 30;
+
+/// Текст страницы `page` (с нуля) и кнопки для перехода между страницами. Серии
+/// сгруппированы по сезонам и упорядочены по номеру. Если страниц меньше, чем `page + 1`,
+/// показывается последняя.
+pub fn build(
+    show: &Show,
+    seen_episodes: &[SeenEpisode],
+    page: usize,
+    language: Language,
+) -> (String, Option<InlineKeyboardMarkup>) {
+    let seen_set: HashSet<&Episode> = seen_episodes.iter().map(SeenEpisode::episode).collect();
+    let mut episodes: Vec<&Episode> = show
+        .episodes()
+        .iter()
+        .filter(|episode| seen_set.contains(episode))
+        .collect();
+    episodes.sort_by_key(|episode| (episode.season(), episode.episode()));
+
+    let mut season_sizes: BTreeMap<u8, (usize, usize)> = BTreeMap::new();
+    for episode in show.episodes() {
+        season_sizes.entry(episode.season()).or_default().1 += 1;
+    }
+    for episode in &episodes {
+        season_sizes.entry(episode.season()).or_default().0 += 1;
+    }
+
+    let pages = episodes.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut lines = vec![format!(
+        "Просмотренные серии: {} из {}",
+        episodes.len(),
+        show.episodes().len()
+    )];
+    if pages > 1 {
+        lines.push(format!("Страница {} из {pages}", page + 1));
+    }
+
+    let mut current_season = None;
+    for episode in episodes.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        if current_season != Some(episode.season()) {
+            current_season = Some(episode.season());
+            let (seen, total) = season_sizes[&episode.season()];
+            lines.push(String::new());
+            lines.push(format!("Сезон {} — {seen} из {total}", episode.season()));
+        }
+
+        lines.push(match title(episode, language) {
+            Some(title) => format!("Серия {} «{title}»", episode.episode()),
+            None => format!("Серия {}", episode.episode()),
+        });
+    }
+
+    let keyboard = (pages > 1).then(|| {
+        let mut buttons = Vec::new();
+        if page > 0 {
+            buttons.push(InlineKeyboardButton::callback(
+                "◀",
+                format!("seen_page={}:{}", show.id(), page - 1),
+            ));
+        }
+        if page + 1 < pages {
+            buttons.push(InlineKeyboardButton::callback(
+                "▶",
+                format!("seen_page={}:{}", show.id(), page + 1),
+            ));
+        }
+
+        InlineKeyboardMarkup::new(vec![buttons])
+    });
+
+    (lines.join("\n"), keyboard)
+}
+
+fn title(episode: &Episode, language: Language) -> Option<&str> {
+    let metadata = episode.metadata()?;
+
+    match language {
+        Language::Russian => metadata
+            .localized_title
+            .as_deref()
+            .or(metadata.title.as_deref()),
+        Language::English => metadata
+            .title
+            .as_deref()
+            .or(metadata.localized_title.as_deref()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application::{Catalogue, SeenEpisodeSource};
+
+    fn seen(codes: &[&str]) -> Vec<SeenEpisode> {
+        codes
+            .iter()
+            .map(|&code| {
+                SeenEpisode::new(
+                    Episode::try_from(code).unwrap(),
+                    None,
+                    SeenEpisodeSource::Legacy,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn build_fn_groups_episodes_by_season() {
+        let catalogue = Catalogue::builtin();
+        let show = catalogue.default_show();
+
+        let (text, keyboard) = build(
+            show,
+            &seen(&["s02e03", "s01e02", "s01e01", "s01e02"]),
+            0,
+            Language::Russian,
+        );
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("Просмотренные серии: 3 из {}", show.episodes().len())
+        );
+        assert!(lines[2].starts_with("Сезон 1 — 2 из "), "text: {text}");
+        assert!(lines[3].starts_with("Серия 1"), "text: {text}");
+        assert!(lines[4].starts_with("Серия 2"), "text: {text}");
+        assert!(lines[6].starts_with("Сезон 2 — 1 из "), "text: {text}");
+        assert!(lines[7].starts_with("Серия 3"), "text: {text}");
+        assert!(keyboard.is_none());
+    }
+
+    #[test]
+    fn build_fn_splits_long_list_into_pages() {
+        let catalogue = Catalogue::builtin();
+        let show = catalogue.default_show();
+        let codes: Vec<&str> = show.episodes().iter().map(Episode::code).collect();
+        let seen_episodes = seen(&codes);
+        let pages = codes.len().div_ceil(PAGE_SIZE);
+
+        let mut seen_lines = 0;
+        for page in 0..pages {
+            let (text, keyboard) = build(show, &seen_episodes, page, Language::English);
+
+            assert!(text.chars().count() < 4096, "page {page} is too long");
+            assert!(text.contains(&format!("Страница {} из {pages}", page + 1)));
+            seen_lines += text
+                .lines()
+                .filter(|line| line.starts_with("Серия"))
+                .count();
+
+            let buttons = &keyboard.expect("keyboard should be shown").inline_keyboard[0];
+            let expected_buttons = if page == 0 || page == pages - 1 { 1 } else { 2 };
+            assert_eq!(buttons.len(), expected_buttons, "page: {page}");
+        }
+        assert_eq!(seen_lines, codes.len());
+
+        let (last_page, _) = build(show, &seen_episodes, pages - 1, Language::English);
+        let (out_of_range, _) = build(show, &seen_episodes, pages + 10, Language::English);
+        assert_eq!(last_page, out_of_range);
+    }
+}
@@ -0,0 +1,187 @@
+//! Кнопки со ссылками на предложенную серию.
+
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup};
+
+/// Ссылка, которую собрал сервис для просмотра.
+pub struct WatchLink<'a> {
+    pub provider_id: &'a str,
+    pub label: &'a str,
+    pub url: String,
+}
+
+/// По строке с кнопкой на каждую ссылку в том же порядке. Пустые ссылки, например из пустого
+/// шаблона, и ссылки, которые не разбираются как URL, пропускаются. Если сервис один,
+/// его название на кнопке не нужно.
+pub fn build(links: Vec<WatchLink>, single_provider: bool) -> Vec<Vec<InlineKeyboardButton>> {
+    let mut rows = Vec::new();
+    for link in links {
+        if link.url.trim().is_empty() {
+            continue;
+        }
+
+        let url = match url::Url::parse(&link.url) {
+            Ok(url) => url,
+            Err(err) => {
+                tracing::warn!(
+                    provider = link.provider_id,
+                    url = link.url,
+                    error = err.to_string(),
+                    "сервис для просмотра собрал некорректную ссылку"
+                );
+                continue;
+            }
+        };
+
+        let text = match single_provider {
+            true => String::from("Смотреть"),
+            false => format!("Смотреть: {}", link.label),
+        };
+        rows.push(vec![InlineKeyboardButton::url(text, url)]);
+    }
+
+    rows
+}
+
+/// Клавиатура предложения, в которой кнопка «Посмотрел» заменена на `button`. Остальные
+/// кнопки, в том числе ссылки на просмотр, остаются на месте. Если кнопки «Посмотрел»
+/// в клавиатуре нет, `button` добавляется отдельной строкой.
+pub fn swap_mark_button(
+    markup: Option<&InlineKeyboardMarkup>,
+    button: InlineKeyboardButton,
+) -> InlineKeyboardMarkup {
+    let mut keyboard = markup
+        .map(|markup| markup.inline_keyboard.clone())
+        .unwrap_or_default();
+
+    let mark_button = keyboard.iter_mut().flatten().find(|button| {
+        matches!(&button.kind, InlineKeyboardButtonKind::CallbackData(data) if data.starts_with("mark_seen="))
+    });
+    match mark_button {
+        Some(mark_button) => *mark_button = button,
+        None => keyboard.push(vec![button]),
+    }
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn link<'a>(label: &'a str, url: &str) -> WatchLink<'a> {
+        WatchLink {
+            provider_id: label,
+            label,
+            url: url.to_string(),
+        }
+    }
+
+    fn buttons(rows: &[Vec<InlineKeyboardButton>]) -> Vec<(&str, &str)> {
+        rows.iter()
+            .flatten()
+            .map(|button| match &button.kind {
+                InlineKeyboardButtonKind::Url(url) => (button.text.as_str(), url.as_str()),
+                other => panic!("unexpected button kind: {other:?}"),
+            })
+            .collect()
+    }
+
+    fn callback_data(rows: &[Vec<InlineKeyboardButton>]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|button| match &button.kind {
+                        InlineKeyboardButtonKind::CallbackData(data) => data.clone(),
+                        InlineKeyboardButtonKind::Url(url) => url.to_string(),
+                        other => panic!("unexpected button kind: {other:?}"),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn swap_mark_button_fn_keeps_links_and_other_buttons() {
+        let mut keyboard = build(vec![link("default", "https://example.com/s01e01")], true);
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            "Посмотрел",
+            "mark_seen=friends:s01e01",
+        )]);
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            "Только 1 сезон",
+            "season_filter=friends:1",
+        )]);
+
+        let result = swap_mark_button(
+            Some(&InlineKeyboardMarkup::new(keyboard)),
+            InlineKeyboardButton::callback("Отменить", "unmark_seen=friends:s01e01"),
+        );
+
+        assert_eq!(
+            callback_data(&result.inline_keyboard),
+            vec![
+                vec![String::from("https://example.com/s01e01")],
+                vec![String::from("unmark_seen=friends:s01e01")],
+                vec![String::from("season_filter=friends:1")],
+            ]
+        );
+    }
+
+    #[test]
+    fn swap_mark_button_fn_adds_button_without_mark_button() {
+        let result = swap_mark_button(
+            None,
+            InlineKeyboardButton::callback("Отменить", "unmark_seen=friends:s01e01"),
+        );
+
+        assert_eq!(
+            callback_data(&result.inline_keyboard),
+            vec![vec![String::from("unmark_seen=friends:s01e01")]]
+        );
+    }
+
+    #[test]
+    fn build_fn_skips_empty_url() {
+        let rows = build(vec![link("default", ""), link("blank", "  ")], true);
+
+        assert!(rows.is_empty(), "rows: {rows:#?}");
+    }
+
+    #[test]
+    fn build_fn_skips_invalid_url() {
+        let rows = build(vec![link("default", "not a url")], true);
+
+        assert!(rows.is_empty(), "rows: {rows:#?}");
+    }
+
+    #[test]
+    fn build_fn_uses_plain_text_for_single_provider() {
+        let rows = build(vec![link("default", "https://example.com/s01e01")], true);
+
+        assert_eq!(
+            buttons(&rows),
+            vec![("Смотреть", "https://example.com/s01e01")]
+        );
+    }
+
+    #[test]
+    fn build_fn_adds_row_per_provider_in_order() {
+        let rows = build(
+            vec![
+                link("Кинопоиск", "https://kino.example.com/s01e01"),
+                link("Сломанный", "::"),
+                link("Netflix", "https://netflix.example.com/s01e01"),
+            ],
+            false,
+        );
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            buttons(&rows),
+            vec![
+                ("Смотреть: Кинопоиск", "https://kino.example.com/s01e01"),
+                ("Смотреть: Netflix", "https://netflix.example.com/s01e01"),
+            ]
+        );
+    }
+}
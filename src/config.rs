@@ -1,3 +1,4 @@
+use crate::{application::Language, watch_url_provider::template::URLTemplate};
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
 
@@ -5,7 +6,128 @@ use std::path::{Path, PathBuf};
 pub struct Config {
     pub bot_token: String,
     pub storage_path: PathBuf,
-    pub watch_url_template: String,
+    #[serde(default)]
+    pub storage_backend: StorageBackend,
+    /// Шаблон ссылки для просмотра, подстановки описаны в [`crate::watch_url_provider::template`].
+    /// Некорректный шаблон не даёт загрузить конфиг. Используется, только если список
+    /// `watch_providers` пуст.
+    #[serde(default, deserialize_with = "deserialize_url_template")]
+    pub watch_url_template: URLTemplate,
+    /// Сервисы для просмотра. Под предложенной серией будет кнопка для каждого из них.
+    /// Если список задан, у сериалов в каталоге не должно быть своего `watch_url_template`.
+    #[serde(default)]
+    pub watch_providers: Vec<WatchProviderConfig>,
+    /// Как часто проверять, что сайты из `watch_providers` отвечают.
+    #[serde(default)]
+    pub watch_health_check: HealthCheckConfig,
+    /// JSON-файл с каталогом серий. Если не задан, используется встроенный список серий.
+    pub episodes_catalogue_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchProviderConfig {
+    /// Под этим идентификатором выбор пользователя хранится в его настройках.
+    pub id: String,
+    /// Название сервиса на кнопке.
+    pub label: String,
+    #[serde(deserialize_with = "deserialize_url_template")]
+    pub template: URLTemplate,
+    /// Язык сервиса, `ru` или `en`. Пользователям с тем же языком названий серий
+    /// сервис предлагается первым.
+    #[serde(default)]
+    pub locale: Option<Language>,
+    /// Откуда брать идентификатор страницы серии для подстановки `{id}` в `template`.
+    #[serde(default)]
+    pub resolver: Option<ResolverConfig>,
+    /// Зеркала сервиса. Если ссылку по `template` собрать не удалось или сайт не отвечает
+    /// на проверки, кнопка ведёт на первое рабочее зеркало.
+    #[serde(default)]
+    pub mirrors: Vec<MirrorConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MirrorConfig {
+    #[serde(deserialize_with = "deserialize_url_template")]
+    pub template: URLTemplate,
+    #[serde(default)]
+    pub resolver: Option<ResolverConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HealthCheckConfig {
+    /// Интервал между проверками, `0` отключает проверки.
+    #[serde(default = "default_health_check_interval_seconds")]
+    pub interval_seconds: u64,
+    /// Сколько ждать ответа сайта, прежде чем считать проверку неудачной.
+    #[serde(default = "default_health_check_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: default_health_check_interval_seconds(),
+            timeout_seconds: default_health_check_timeout_seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolverConfig {
+    pub source: ResolverSource,
+    /// Сколько найденных идентификаторов держать в памяти.
+    #[serde(default = "default_resolver_cache_capacity")]
+    pub cache_capacity: usize,
+    /// Через сколько секунд идентификатор нужно запросить заново.
+    #[serde(default = "default_resolver_cache_ttl_seconds")]
+    pub cache_ttl_seconds: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolverSource {
+    /// JSON-файл вида `{"friends": {"s01e01": "4217"}}`, читается при запуске.
+    File(PathBuf),
+    /// Адрес, который отвечает на GET `{"id": "4217"}`. Это шаблон с теми же подстановками,
+    /// что и у ссылок, например `http://127.0.0.1:8080/ids/{show}/{code}`.
+    Http(#[serde(deserialize_with = "deserialize_url_template")] URLTemplate),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Отдельный текстовый файл для каждого пользователя в папке `storage_path`.
+    #[default]
+    File,
+    /// База SQLite в файле `seen_episodes.sqlite3` внутри папки `storage_path`.
+    Sqlite,
+    /// Хранение в памяти процесса, без записи на диск. Всё забывается при перезапуске.
+    Memory,
+}
+
+fn default_resolver_cache_capacity() -> usize {
+    1024
+}
+
+fn default_resolver_cache_ttl_seconds() -> u64 {
+    60 * 60
+}
+
+fn default_health_check_interval_seconds() -> u64 {
+    5 * 60
+}
+
+fn default_health_check_timeout_seconds() -> u64 {
+    10
+}
+
+fn deserialize_url_template<'de, D>(deserializer: D) -> Result<URLTemplate, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let template = String::deserialize(deserializer)?;
+
+    template.parse().map_err(serde::de::Error::custom)
 }
 
 pub fn new(config_path: &Path) -> Result<Config, config::ConfigError> {
@@ -23,3 +145,45 @@ pub fn new(config_path: &Path) -> Result<Config, config::ConfigError> {
         .build()?
         .try_deserialize()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_config(watch_url_template: &str) -> (PathBuf, TempDir) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let config_path = temp_dir.path().join("config.json");
+        std::fs::write(
+            &config_path,
+            format!(
+                r#"{{"bot_token": "token", "storage_path": "seen_episodes", "watch_url_template": "{watch_url_template}"}}"#
+            ),
+        )
+        .unwrap();
+
+        (config_path, temp_dir)
+    }
+
+    #[test]
+    fn new_fn_parses_watch_url_template() {
+        let (config_path, _temp_dir) = write_config("https://example.com/{show}/{code}");
+
+        let result = new(&config_path);
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(
+            result.unwrap().watch_url_template,
+            "https://example.com/{show}/{code}".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn new_fn_rejects_unknown_placeholders() {
+        let (config_path, _temp_dir) = write_config("https://example.com/{serie}");
+
+        let result = new(&config_path);
+
+        assert!(result.is_err(), "result is ok: {result:#?}");
+    }
+}
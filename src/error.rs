@@ -1,8 +1,19 @@
 #[derive(Debug)]
 pub enum Error {
     NoUnseenEpisodes,
+    EpisodeParseError(String),
+    UnknownEpisode(String),
+    UnknownShow(String),
+    SeasonFilterError(String),
+    EpisodeRangeError(String),
     FileError(std::io::Error),
+    DatabaseError(rusqlite::Error),
+    StorageSchemaError(String),
+    CatalogueError(String),
     CallbackCommandParseError(String),
+    WatchURLTemplateError(String),
+    WatchProviderError(String),
+    WatchURLResolveError(String),
 }
 
 impl std::error::Error for Error {}
@@ -11,12 +22,45 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let as_string = match self {
             Error::NoUnseenEpisodes => "Не осталось непросмотренных эпизодов".to_string(),
+            Error::EpisodeParseError(error) => {
+                format!("не удалось распарсить код серии: {error}")
+            }
+            Error::UnknownEpisode(code) => {
+                format!("серии нет в каталоге: code={code}")
+            }
+            Error::UnknownShow(show_id) => {
+                format!("сериала нет в каталоге: id={show_id}")
+            }
+            Error::SeasonFilterError(error) => {
+                format!("некорректный фильтр сезонов: {error}")
+            }
+            Error::EpisodeRangeError(error) => {
+                format!("некорректный список серий: {error}")
+            }
             Error::FileError(error) => {
                 format!("Ошибка при работе с файлами: {error}")
             }
+            Error::DatabaseError(error) => {
+                format!("Ошибка при работе с базой данных: {error}")
+            }
+            Error::StorageSchemaError(error) => {
+                format!("Ошибка схемы хранилища: {error}")
+            }
+            Error::CatalogueError(error) => {
+                format!("Ошибка в каталоге серий: {error}")
+            }
             Error::CallbackCommandParseError(error) => {
                 format!("не удалось распарсить команду из колбека: {error}")
             }
+            Error::WatchURLTemplateError(error) => {
+                format!("некорректный шаблон ссылки для просмотра: {error}")
+            }
+            Error::WatchProviderError(error) => {
+                format!("некорректный список сервисов для просмотра: {error}")
+            }
+            Error::WatchURLResolveError(error) => {
+                format!("не удалось получить ссылку для просмотра: {error}")
+            }
         };
 
         write!(f, "{}", as_string)
@@ -28,3 +72,9 @@ impl From<std::io::Error> for Error {
         Error::FileError(err)
     }
 }
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::DatabaseError(err)
+    }
+}
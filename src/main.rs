@@ -24,13 +24,82 @@ async fn main() {
         }
     };
 
-    let application = Arc::new(application::new(config.storage_path));
-    let watch_url_provider = Arc::new(watch_url_provider::provider_1::new(
+    let store: Arc<dyn application::storage::SeenEpisodesStore + Send + Sync> =
+        match config.storage_backend {
+            config::StorageBackend::File => {
+                Arc::new(application::storage::file::new(config.storage_path))
+            }
+            config::StorageBackend::Sqlite => {
+                let database_path = config.storage_path.join("seen_episodes.sqlite3");
+                match application::storage::sqlite::new(&database_path) {
+                    Ok(store) => Arc::new(store),
+                    Err(err) => {
+                        tracing::error!("{err}");
+                        return;
+                    }
+                }
+            }
+            config::StorageBackend::Memory => Arc::new(application::storage::memory::new()),
+        };
+
+    tracing::info!("Migrating storage...");
+    match store.migrate() {
+        Ok(report) => tracing::info!(
+            from_version = report.from_version,
+            to_version = report.to_version,
+            migrated_users = report.migrated_users,
+            backup_path = report
+                .backup_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().to_string()),
+            "storage migration finished: schema v{} -> v{}, users migrated: {}",
+            report.from_version,
+            report.to_version,
+            report.migrated_users,
+        ),
+        Err(err) => {
+            tracing::error!("{err}");
+            return;
+        }
+    }
+
+    tracing::info!("Loading episodes catalogue...");
+    let catalogue = match application::catalogue::load(config.episodes_catalogue_path.as_deref()) {
+        Ok(catalogue) => catalogue,
+        Err(err) => {
+            tracing::error!("{err}");
+            return;
+        }
+    };
+    tracing::info!(
+        shows = catalogue.shows().len(),
+        episodes = catalogue
+            .shows()
+            .iter()
+            .map(|show| show.episodes().len())
+            .sum::<usize>(),
+        "episodes catalogue loaded"
+    );
+
+    let watch_providers = match watch_url_provider::new(
         config.watch_url_template,
-    ));
+        config.watch_providers,
+        &catalogue,
+    ) {
+        Ok(watch_providers) => Arc::new(watch_providers),
+        Err(err) => {
+            tracing::error!("{err}");
+            return;
+        }
+    };
+    watch_url_provider::health::spawn(
+        watch_providers.health_targets().to_vec(),
+        &config.watch_health_check,
+    );
+    let application = Arc::new(application::new(store, catalogue));
 
     tracing::info!("Starting bot...");
-    bot::new(config.bot_token, application, watch_url_provider)
+    bot::new(config.bot_token, application, watch_providers)
         .await
         .dispatch()
         .await;
@@ -1,7 +1,559 @@
-use crate::application::Episode;
+use crate::{
+    application::{Catalogue, Episode, Language, Show, UserSettings},
+    config::{ResolverConfig, WatchProviderConfig},
+    error::Error,
+};
+use async_trait::async_trait;
+use health::Health;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use template::URLTemplate;
 
+pub mod cache;
+pub mod fallback;
+pub mod health;
 pub mod provider_1;
+pub mod resolver;
+pub mod template;
+#[cfg(test)]
+mod test_server;
 
+/// Собирает ссылку на серию. Сервису может понадобиться сходить в сеть, и тогда
+/// ссылку не всегда удаётся получить.
+#[async_trait]
 pub trait WatchURLProvider {
-    fn build_url(&self, episode: &Episode) -> String;
+    async fn build_url(&self, show: &Show, episode: &Episode) -> Result<String, Error>;
+}
+
+#[async_trait]
+impl WatchURLProvider for URLTemplate {
+    async fn build_url(&self, show: &Show, episode: &Episode) -> Result<String, Error> {
+        Ok(self.render(show, episode))
+    }
+}
+
+/// Идентификатор сервиса, который собирается из `watch_url_template`, когда список
+/// сервисов в конфиге не задан.
+pub const DEFAULT_PROVIDER_ID: &str = "default";
+
+/// Собирает сервисы для просмотра из конфига. Если список `watch_providers` пуст,
+/// используется единственный сервис с шаблоном `watch_url_template` и шаблонами
+/// сериалов из каталога. Шаблоны сериалов из каталога вместе со списком сервисов
+/// не поддерживаются: у каждого сервиса свои ссылки, и один шаблон сериала на все
+/// сервисы дал бы одинаковые кнопки.
+pub fn new(
+    watch_url_template: URLTemplate,
+    watch_providers: Vec<WatchProviderConfig>,
+    catalogue: &Catalogue,
+) -> Result<WatchProviders, Error> {
+    if watch_providers.is_empty() {
+        check_no_page_id(DEFAULT_PROVIDER_ID, &watch_url_template)?;
+        let url_provider = provider_1::new(watch_url_template, catalogue)?;
+
+        // единственный сервис тоже проверяется, чтобы недоступность сайта была видна в логах
+        let health = Arc::new(Health::default());
+        let health_targets = health_check_url(
+            url_provider.template_for(catalogue.default_show()),
+            catalogue,
+        )
+        .map(|url| health::Target::new(String::from(DEFAULT_PROVIDER_ID), url, Arc::clone(&health)))
+        .into_iter()
+        .collect();
+        let member = fallback::Member::new(
+            String::from(DEFAULT_PROVIDER_ID),
+            Box::new(url_provider),
+            Arc::clone(&health),
+        );
+
+        return Ok(WatchProviders {
+            providers: vec![WatchProvider {
+                id: String::from(DEFAULT_PROVIDER_ID),
+                label: String::from("Смотреть"),
+                locale: None,
+                url_provider: Box::new(fallback::new(vec![member])),
+                health: vec![health],
+            }],
+            health_targets,
+        });
+    }
+
+    if let Some(show) = catalogue
+        .shows()
+        .iter()
+        .find(|show| show.watch_url_template().is_some())
+    {
+        return Err(Error::WatchProviderError(format!(
+            "watch_url_template сериала в каталоге нельзя использовать вместе с watch_providers: show={}",
+            show.id()
+        )));
+    }
+
+    let mut ids = HashSet::new();
+    for config in &watch_providers {
+        if config.id.trim().is_empty() || config.label.trim().is_empty() {
+            return Err(Error::WatchProviderError(format!(
+                "у сервиса должны быть id и label: id={}",
+                config.id
+            )));
+        }
+        if !ids.insert(config.id.as_str()) {
+            return Err(Error::WatchProviderError(format!(
+                "сервис встречается в конфиге дважды: id={}",
+                config.id
+            )));
+        }
+    }
+
+    let mut providers = Vec::new();
+    let mut health_targets = Vec::new();
+    for config in watch_providers {
+        // основной шаблон и зеркала становятся звеньями одной цепочки
+        let sources = std::iter::once((config.template, config.resolver)).chain(
+            config
+                .mirrors
+                .into_iter()
+                .map(|mirror| (mirror.template, mirror.resolver)),
+        );
+
+        let mut members = Vec::new();
+        let mut healths = Vec::new();
+        for (index, (template, resolver)) in sources.enumerate() {
+            let name = match index {
+                0 => config.id.clone(),
+                index => format!("{}#{index}", config.id),
+            };
+            let health = Arc::new(Health::default());
+            if let Some(url) = health_check_url(&template, catalogue) {
+                health_targets.push(health::Target::new(name.clone(), url, Arc::clone(&health)));
+            }
+
+            let url_provider = build_url_provider(&name, template, resolver)?;
+            healths.push(Arc::clone(&health));
+            members.push(fallback::Member::new(name, url_provider, health));
+        }
+
+        providers.push(WatchProvider {
+            id: config.id,
+            label: config.label,
+            locale: config.locale,
+            url_provider: Box::new(fallback::new(members)),
+            health: healths,
+        });
+    }
+
+    Ok(WatchProviders {
+        providers,
+        health_targets,
+    })
+}
+
+fn build_url_provider(
+    name: &str,
+    template: URLTemplate,
+    resolver: Option<ResolverConfig>,
+) -> Result<Box<dyn WatchURLProvider + Send + Sync>, Error> {
+    match resolver {
+        Some(resolver) => Ok(Box::new(resolver::new(template, resolver)?)),
+        None => {
+            check_no_page_id(name, &template)?;
+            Ok(Box::new(template))
+        }
+    }
+}
+
+/// Ссылка на первую серию первого сериала, по ней проверяется, что сайт отвечает.
+/// Идентификатор страницы для проверки не запрашивается, сайту достаточно ответить хоть как-то.
+fn health_check_url(template: &URLTemplate, catalogue: &Catalogue) -> Option<String> {
+    let show = catalogue.default_show();
+    let url = template.render(show, show.episodes().first()?);
+
+    match url::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Some(url),
+        _ => None,
+    }
+}
+
+/// `{id}` без `resolver` подставился бы кодом серии, и ссылка тихо вела бы не туда.
+fn check_no_page_id(provider_id: &str, template: &URLTemplate) -> Result<(), Error> {
+    match template.uses_page_id() {
+        true => Err(Error::WatchProviderError(format!(
+            "подстановка {{id}} работает только вместе с resolver: id={provider_id}"
+        ))),
+        false => Ok(()),
+    }
+}
+
+/// Сервис для просмотра серий, на который бот даёт ссылки.
+pub struct WatchProvider {
+    id: String,
+    label: String,
+    locale: Option<Language>,
+    url_provider: Box<dyn WatchURLProvider + Send + Sync>,
+    // состояние основного сайта и зеркал
+    health: Vec<Arc<Health>>,
+}
+
+impl WatchProvider {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Название сервиса для кнопок и настроек.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Язык, на котором сервис показывает сериал, если он известен.
+    pub fn locale(&self) -> Option<Language> {
+        self.locale
+    }
+
+    /// Доступен ли по результатам фоновых проверок хотя бы один сайт сервиса.
+    pub fn is_healthy(&self) -> bool {
+        self.health.iter().any(|health| health.is_healthy())
+    }
+
+    pub async fn build_url(&self, show: &Show, episode: &Episode) -> Result<String, Error> {
+        self.url_provider.build_url(show, episode).await
+    }
+}
+
+/// Сервисы для просмотра в порядке из конфига. Всегда есть хотя бы один.
+pub struct WatchProviders {
+    providers: Vec<WatchProvider>,
+    health_targets: Vec<health::Target>,
+}
+
+impl WatchProviders {
+    /// Сервисы в порядке из конфига. Список не бывает пустым: при пустом `watch_providers`
+    /// в нём единственный сервис по шаблону `watch_url_template`.
+    pub fn list(&self) -> &[WatchProvider] {
+        &self.providers
+    }
+
+    /// Сайты сервисов и их зеркал, которые нужно проверять в фоне, см. [`health::spawn`].
+    pub fn health_targets(&self) -> &[health::Target] {
+        &self.health_targets
+    }
+
+    /// Сервисы в порядке, в котором их стоит предлагать пользователю: сначала выбранный
+    /// в настройках, потом сервисы на языке пользователя, потом остальные.
+    pub fn ordered_for(&self, settings: &UserSettings) -> Vec<&WatchProvider> {
+        let mut providers: Vec<&WatchProvider> = self.providers.iter().collect();
+        providers.sort_by_key(|provider| {
+            (
+                settings.watch_provider.as_deref() != Some(provider.id()),
+                provider.locale() != Some(settings.language),
+            )
+        });
+
+        providers
+    }
+
+    /// Сервис по умолчанию для пользователя, первый из [`Self::ordered_for`].
+    pub fn default_for(&self, settings: &UserSettings) -> &WatchProvider {
+        self.ordered_for(settings)
+            .first()
+            .copied()
+            .unwrap_or(&self.providers[0])
+    }
+
+    /// Сервис, следующий в списке из конфига за сервисом по умолчанию для пользователя,
+    /// после последнего снова первый.
+    pub fn next_after(&self, settings: &UserSettings) -> &WatchProvider {
+        let current = self.default_for(settings);
+        self.providers
+            .iter()
+            .position(|provider| provider.id() == current.id())
+            .and_then(|index| self.providers.get((index + 1) % self.providers.len()))
+            .unwrap_or(current)
+    }
+
+    /// Ссылки на серию в порядке [`Self::ordered_for`]. Сервисы опрашиваются одновременно,
+    /// и на всех вместе даётся не больше `deadline`: сервисы, которые не успели или не смогли
+    /// собрать ссылку, пропускаются, чтобы не задерживать ответ пользователю.
+    pub async fn build_urls(
+        &self,
+        settings: &UserSettings,
+        show: &Show,
+        episode: &Episode,
+        deadline: Duration,
+    ) -> Vec<(&WatchProvider, String)> {
+        let deadline = tokio::time::Instant::now() + deadline;
+        let providers = self.ordered_for(settings);
+        let results =
+            futures::future::join_all(providers.iter().map(|provider| {
+                tokio::time::timeout_at(deadline, provider.build_url(show, episode))
+            }))
+            .await;
+
+        providers
+            .into_iter()
+            .zip(results)
+            .filter_map(|(provider, result)| match result {
+                Ok(Ok(url)) => Some((provider, url)),
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        provider = provider.id(),
+                        error = err.to_string(),
+                        "сервис для просмотра не смог собрать ссылку"
+                    );
+                    None
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        provider = provider.id(),
+                        "сервис для просмотра не успел собрать ссылку"
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{MirrorConfig, ResolverSource};
+
+    fn provider_config(id: &str, locale: Option<Language>) -> WatchProviderConfig {
+        WatchProviderConfig {
+            id: id.to_string(),
+            label: id.to_uppercase(),
+            template: format!("https://{id}.example.com/{{code}}")
+                .parse()
+                .unwrap(),
+            locale,
+            resolver: None,
+            mirrors: Vec::new(),
+        }
+    }
+
+    fn ids(providers: Vec<&WatchProvider>) -> Vec<&str> {
+        providers.into_iter().map(WatchProvider::id).collect()
+    }
+
+    #[tokio::test]
+    async fn new_fn_uses_legacy_template_without_providers() {
+        let catalogue = Catalogue::builtin();
+
+        let result = new(
+            "https://example.com/{season}".parse().unwrap(),
+            Vec::new(),
+            &catalogue,
+        );
+
+        assert!(result.is_ok(), "result is error: {:#?}", result.err());
+        let providers = result.unwrap();
+        assert_eq!(
+            ids(providers.list().iter().collect()),
+            vec![DEFAULT_PROVIDER_ID]
+        );
+        let show = catalogue.default_show();
+        assert_eq!(
+            providers.list()[0]
+                .build_url(show, &show.episodes()[0])
+                .await
+                .unwrap(),
+            "https://example.com/1"
+        );
+        let targets: Vec<(&str, &str)> = providers
+            .health_targets()
+            .iter()
+            .map(|target| (target.name(), target.url()))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![(DEFAULT_PROVIDER_ID, "https://example.com/1")]
+        );
+        assert!(providers.list()[0].is_healthy());
+    }
+
+    #[test]
+    fn new_fn_skips_health_check_for_empty_legacy_template() {
+        let result = new(URLTemplate::default(), Vec::new(), &Catalogue::builtin());
+
+        assert!(result.is_ok(), "result is error: {:#?}", result.err());
+        assert!(result.unwrap().health_targets().is_empty());
+    }
+
+    #[tokio::test]
+    async fn new_fn_builds_fallback_chain_from_mirrors() {
+        let catalogue = Catalogue::builtin();
+        let mut config = provider_config("kino", None);
+        config.template = "".parse().unwrap();
+        config.mirrors = vec![MirrorConfig {
+            template: "https://mirror.example.com/{code}".parse().unwrap(),
+            resolver: None,
+        }];
+
+        let result = new(URLTemplate::default(), vec![config], &catalogue);
+
+        assert!(result.is_ok(), "result is error: {:#?}", result.err());
+        let providers = result.unwrap();
+        let show = catalogue.default_show();
+        assert_eq!(
+            providers.list()[0]
+                .build_url(show, &show.episodes()[0])
+                .await
+                .unwrap(),
+            "https://mirror.example.com/s01e01"
+        );
+        // у пустого шаблона проверять нечего
+        let targets: Vec<(&str, &str)> = providers
+            .health_targets()
+            .iter()
+            .map(|target| (target.name(), target.url()))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![("kino#1", "https://mirror.example.com/s01e01")]
+        );
+    }
+
+    #[test]
+    fn new_fn_rejects_show_templates_with_providers() {
+        let catalogue = Catalogue::from_json(
+            r#"{"shows": [
+                {"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01"}]},
+                {"id": "the-office", "name": "Офис", "watch_url_template": "https://office.example.com/{code}", "episodes": [{"code": "s01e01"}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let result = new(
+            URLTemplate::default(),
+            vec![provider_config("kino", None)],
+            &catalogue,
+        );
+
+        assert!(matches!(result, Err(Error::WatchProviderError(_))));
+    }
+
+    #[test]
+    fn new_fn_rejects_page_id_without_resolver() {
+        let mut config = provider_config("kino", None);
+        config.template = "https://kino.example.com/{id}".parse().unwrap();
+
+        let result = new(URLTemplate::default(), vec![config], &Catalogue::builtin());
+
+        assert!(matches!(result, Err(Error::WatchProviderError(_))));
+    }
+
+    #[tokio::test]
+    async fn watch_providers_build_urls_fn_drops_providers_after_deadline() {
+        let server = test_server::start(|_, _| {
+            std::thread::sleep(Duration::from_secs(2));
+            (200, String::from(r#"{"id": 4217}"#))
+        });
+        let mut slow = provider_config("slow", None);
+        slow.template = "https://slow.example.com/{id}".parse().unwrap();
+        slow.resolver = Some(ResolverConfig {
+            source: ResolverSource::Http(server.url("/ids/{code}").parse().unwrap()),
+            cache_capacity: 16,
+            cache_ttl_seconds: 60,
+        });
+        let catalogue = Catalogue::builtin();
+        let providers = new(
+            URLTemplate::default(),
+            vec![slow, provider_config("fast", None)],
+            &catalogue,
+        )
+        .unwrap();
+        let show = catalogue.default_show();
+
+        let started_at = std::time::Instant::now();
+        let urls = providers
+            .build_urls(
+                &UserSettings::default(),
+                show,
+                &show.episodes()[0],
+                Duration::from_millis(200),
+            )
+            .await;
+
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+        let urls: Vec<(&str, &str)> = urls
+            .iter()
+            .map(|(provider, url)| (provider.id(), url.as_str()))
+            .collect();
+        assert_eq!(urls, vec![("fast", "https://fast.example.com/s01e01")]);
+    }
+
+    #[test]
+    fn new_fn_rejects_duplicate_ids() {
+        let result = new(
+            URLTemplate::default(),
+            vec![provider_config("kino", None), provider_config("kino", None)],
+            &Catalogue::builtin(),
+        );
+
+        assert!(matches!(result, Err(Error::WatchProviderError(_))));
+    }
+
+    #[test]
+    fn watch_providers_ordered_for_fn_puts_chosen_and_same_language_first() {
+        let providers = new(
+            URLTemplate::default(),
+            vec![
+                provider_config("netflix", Some(Language::English)),
+                provider_config("kino", Some(Language::Russian)),
+                provider_config("other", None),
+            ],
+            &Catalogue::builtin(),
+        )
+        .unwrap();
+        let mut settings = UserSettings::default();
+
+        assert_eq!(
+            ids(providers.ordered_for(&settings)),
+            vec!["kino", "netflix", "other"]
+        );
+
+        settings.watch_provider = Some(String::from("other"));
+        assert_eq!(
+            ids(providers.ordered_for(&settings)),
+            vec!["other", "kino", "netflix"]
+        );
+        assert_eq!(providers.default_for(&settings).id(), "other");
+
+        // выбранного сервиса больше нет в конфиге
+        settings.watch_provider = Some(String::from("removed"));
+        settings.language = Language::English;
+        assert_eq!(providers.default_for(&settings).id(), "netflix");
+    }
+
+    #[test]
+    fn watch_providers_next_after_fn_cycles_through_providers() {
+        let providers = new(
+            URLTemplate::default(),
+            vec![
+                provider_config("netflix", Some(Language::English)),
+                provider_config("kino", Some(Language::Russian)),
+                provider_config("other", None),
+            ],
+            &Catalogue::builtin(),
+        )
+        .unwrap();
+        let mut settings = UserSettings::default();
+
+        let mut picked = Vec::new();
+        for _ in 0..3 {
+            let next = providers.next_after(&settings).id();
+            picked.push(next);
+            settings.watch_provider = Some(next.to_string());
+        }
+
+        assert_eq!(picked, vec!["other", "netflix", "kino"]);
+    }
+
+    #[test]
+    fn watch_providers_next_after_fn_keeps_single_provider() {
+        let providers = new(URLTemplate::default(), Vec::new(), &Catalogue::builtin()).unwrap();
+
+        assert_eq!(
+            providers.next_after(&UserSettings::default()).id(),
+            providers.list()[0].id()
+        );
+    }
 }
@@ -0,0 +1,129 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// LRU-кеш, в котором запись живёт не дольше `ttl`. Когда записей становится больше
+/// `capacity`, вытесняется та, к которой дольше всего не обращались.
+///
+/// Текущее время передаётся снаружи, чтобы кеш можно было проверить без ожидания.
+pub struct Cache<K, V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<K, Entry<V>>,
+    // ключи от давно использованных к недавно использованным
+    order: VecDeque<K>,
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Cache<K, V> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: &K, now: Instant) -> Option<V> {
+        let entry = self.entries.get(key)?;
+        if entry.expires_at <= now {
+            self.entries.remove(key);
+            self.forget(key);
+            return None;
+        }
+
+        let value = entry.value.clone();
+        self.forget(key);
+        self.order.push_back(key.clone());
+
+        Some(value)
+    }
+
+    pub fn insert(&mut self, key: K, value: V, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let entry = Entry {
+            value,
+            expires_at: now + self.ttl,
+        };
+        if self.entries.insert(key.clone(), entry).is_some() {
+            self.forget(&key);
+        }
+        self.order.push_back(key);
+
+        while self.entries.len() > self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn forget(&mut self, key: &K) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            self.order.remove(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache_insert_fn_evicts_least_recently_used() {
+        let now = Instant::now();
+        let mut cache = Cache::new(2, Duration::from_secs(60));
+
+        cache.insert("a", 1, now);
+        cache.insert("b", 2, now);
+        assert_eq!(cache.get(&"a", now), Some(1));
+        cache.insert("c", 3, now);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"a", now), Some(1));
+        assert_eq!(cache.get(&"b", now), None);
+        assert_eq!(cache.get(&"c", now), Some(3));
+    }
+
+    #[test]
+    fn cache_get_fn_drops_expired_entries() {
+        let now = Instant::now();
+        let mut cache = Cache::new(2, Duration::from_secs(60));
+
+        cache.insert("a", 1, now);
+
+        assert_eq!(cache.get(&"a", now + Duration::from_secs(59)), Some(1));
+        assert_eq!(cache.get(&"a", now + Duration::from_secs(60)), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_insert_fn_replaces_value_and_ttl() {
+        let now = Instant::now();
+        let mut cache = Cache::new(2, Duration::from_secs(60));
+
+        cache.insert("a", 1, now);
+        cache.insert("a", 2, now + Duration::from_secs(30));
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&"a", now + Duration::from_secs(80)), Some(2));
+    }
+}
@@ -0,0 +1,152 @@
+//! Сервис из нескольких зеркал, которые пробуются по очереди.
+
+use super::{WatchURLProvider, health::Health};
+use crate::{
+    application::{Episode, Show},
+    error::Error,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub fn new(members: Vec<Member>) -> Provider {
+    Provider { members }
+}
+
+/// Зеркало сервиса и его состояние по результатам фоновых проверок.
+pub struct Member {
+    name: String,
+    provider: Box<dyn WatchURLProvider + Send + Sync>,
+    health: Arc<Health>,
+}
+
+impl Member {
+    pub fn new(
+        name: String,
+        provider: Box<dyn WatchURLProvider + Send + Sync>,
+        health: Arc<Health>,
+    ) -> Self {
+        Self {
+            name,
+            provider,
+            health,
+        }
+    }
+}
+
+/// Отдаёт первую ссылку, которую удалось собрать. Доступные зеркала пробуются раньше
+/// недоступных, а недоступные всё равно пробуются последними: ссылка на сайт, который
+/// может уже поднялся, лучше, чем никакой.
+pub struct Provider {
+    members: Vec<Member>,
+}
+
+#[async_trait]
+impl WatchURLProvider for Provider {
+    async fn build_url(&self, show: &Show, episode: &Episode) -> Result<String, Error> {
+        let healthy = self
+            .members
+            .iter()
+            .filter(|member| member.health.is_healthy());
+        let unhealthy = self
+            .members
+            .iter()
+            .filter(|member| !member.health.is_healthy());
+
+        let mut last_error = None;
+        for member in healthy.chain(unhealthy) {
+            match member.provider.build_url(show, episode).await {
+                Ok(url) if url.trim().is_empty() => {}
+                Ok(url) => return Ok(url),
+                Err(err) => {
+                    tracing::warn!(
+                        provider = member.name,
+                        error = err.to_string(),
+                        "зеркало сервиса для просмотра не смогло собрать ссылку"
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        match last_error {
+            Some(err) => Err(err),
+            None => Ok(String::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        application::Catalogue,
+        watch_url_provider::{health::FAILURE_THRESHOLD, template::URLTemplate},
+    };
+
+    struct FailingProvider;
+
+    #[async_trait]
+    impl WatchURLProvider for FailingProvider {
+        async fn build_url(&self, _show: &Show, _episode: &Episode) -> Result<String, Error> {
+            Err(Error::WatchURLResolveError(String::from(
+                "сайт не отвечает",
+            )))
+        }
+    }
+
+    fn member(name: &str, provider: Box<dyn WatchURLProvider + Send + Sync>) -> Member {
+        Member::new(name.to_string(), provider, Arc::new(Health::default()))
+    }
+
+    fn template(template: &str) -> Box<dyn WatchURLProvider + Send + Sync> {
+        Box::new(template.parse::<URLTemplate>().unwrap())
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_falls_back_to_next_member_on_error() {
+        let catalogue = Catalogue::builtin();
+        let show = catalogue.default_show();
+        let provider = new(vec![
+            member("main", Box::new(FailingProvider)),
+            member("empty", template("")),
+            member("mirror", template("https://mirror.example.com/{code}")),
+        ]);
+
+        let result = provider.build_url(show, &show.episodes()[0]).await;
+
+        assert!(result.is_ok(), "result is error: {result:#?}");
+        assert_eq!(result.unwrap(), "https://mirror.example.com/s01e01");
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_tries_unhealthy_members_last() {
+        let catalogue = Catalogue::builtin();
+        let show = catalogue.default_show();
+        let main = member("main", template("https://main.example.com/{code}"));
+        for _ in 0..FAILURE_THRESHOLD {
+            main.health.record_failure();
+        }
+        let provider = new(vec![
+            main,
+            member("mirror", template("https://mirror.example.com/{code}")),
+        ]);
+
+        let result = provider.build_url(show, &show.episodes()[0]).await;
+
+        assert_eq!(result.unwrap(), "https://mirror.example.com/s01e01");
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_returns_last_error_when_all_members_fail() {
+        let catalogue = Catalogue::builtin();
+        let show = catalogue.default_show();
+        let provider = new(vec![
+            member("main", Box::new(FailingProvider)),
+            member("mirror", Box::new(FailingProvider)),
+        ]);
+
+        let result = provider.build_url(show, &show.episodes()[0]).await;
+
+        assert!(matches!(result, Err(Error::WatchURLResolveError(_))));
+    }
+}
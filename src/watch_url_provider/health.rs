@@ -0,0 +1,263 @@
+//! Фоновая проверка того, что сайты для просмотра отвечают.
+//!
+//! Раз в `interval_seconds` на ссылку каждого сервиса отправляется HEAD-запрос. Сервис
+//! считается недоступным после [`FAILURE_THRESHOLD`] неудачных проверок подряд и снова
+//! доступным после первой удачной. Ответ с кодом меньше 500 считается удачным: сайт
+//! работает, даже если именно такой страницы на нём нет.
+
+use crate::config::HealthCheckConfig;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+pub const FAILURE_THRESHOLD: u32 = 2;
+
+/// Состояние сервиса по результатам проверок. До первой проверки сервис считается доступным.
+#[derive(Debug)]
+pub struct Health {
+    healthy: AtomicBool,
+    consecutive_failures: AtomicU32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            healthy: AtomicBool::new(true),
+            consecutive_failures: AtomicU32::new(0),
+        }
+    }
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        self.healthy.store(true, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= FAILURE_THRESHOLD {
+            self.healthy.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Что проверять: название сервиса для логов, ссылка и состояние, которое обновляет проверка.
+#[derive(Debug, Clone)]
+pub struct Target {
+    name: String,
+    url: String,
+    health: Arc<Health>,
+}
+
+impl Target {
+    pub fn new(name: String, url: String, health: Arc<Health>) -> Self {
+        Self { name, url, health }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+}
+
+/// Запускает проверки в фоне. Ничего не запускает, если проверять нечего
+/// или `interval_seconds` равен нулю.
+pub fn spawn(targets: Vec<Target>, config: &HealthCheckConfig) -> Option<JoinHandle<()>> {
+    if targets.is_empty() || config.interval_seconds == 0 {
+        return None;
+    }
+
+    let client = match build_client(Duration::from_secs(config.timeout_seconds)) {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::error!(
+                error = err.to_string(),
+                "не удалось запустить проверку сервисов для просмотра"
+            );
+            return None;
+        }
+    };
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_seconds));
+    Some(tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            check_all(&client, &targets).await;
+        }
+    }))
+}
+
+pub fn build_client(timeout: Duration) -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+}
+
+/// Проверяет все сервисы по очереди и пишет в лог итог проверки.
+pub async fn check_all(client: &reqwest::Client, targets: &[Target]) {
+    for target in targets {
+        check(client, target).await;
+    }
+
+    let unhealthy: Vec<&str> = targets
+        .iter()
+        .filter(|target| !target.health.is_healthy())
+        .map(Target::name)
+        .collect();
+    tracing::info!(
+        healthy = targets.len() - unhealthy.len(),
+        unhealthy = unhealthy.len(),
+        unhealthy_providers = unhealthy.join(","),
+        "проверка сервисов для просмотра завершена"
+    );
+}
+
+pub async fn check(client: &reqwest::Client, target: &Target) {
+    let was_healthy = target.health.is_healthy();
+
+    match client.head(&target.url).send().await {
+        Ok(response) if !response.status().is_server_error() => {
+            target.health.record_success();
+            tracing::debug!(
+                provider = target.name,
+                status = response.status().as_u16(),
+                "сервис для просмотра отвечает"
+            );
+        }
+        Ok(response) => {
+            target.health.record_failure();
+            tracing::debug!(
+                provider = target.name,
+                status = response.status().as_u16(),
+                consecutive_failures = target.health.consecutive_failures(),
+                "сервис для просмотра ответил ошибкой"
+            );
+        }
+        Err(err) => {
+            target.health.record_failure();
+            tracing::debug!(
+                provider = target.name,
+                error = err.to_string(),
+                consecutive_failures = target.health.consecutive_failures(),
+                "сервис для просмотра не ответил"
+            );
+        }
+    }
+
+    match (was_healthy, target.health.is_healthy()) {
+        (true, false) => tracing::warn!(
+            provider = target.name,
+            url = target.url,
+            consecutive_failures = target.health.consecutive_failures(),
+            "сервис для просмотра недоступен"
+        ),
+        (false, true) => tracing::info!(
+            provider = target.name,
+            url = target.url,
+            "сервис для просмотра снова доступен"
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::watch_url_provider::test_server;
+    use std::sync::atomic::AtomicU16;
+
+    fn build_target(url: String) -> Target {
+        Target::new(String::from("kino"), url, Arc::new(Health::default()))
+    }
+
+    #[tokio::test]
+    async fn check_fn_marks_target_unhealthy_after_repeated_server_errors() {
+        let status = Arc::new(AtomicU16::new(503));
+        let server_status = Arc::clone(&status);
+        let server =
+            test_server::start(move |_, _| (server_status.load(Ordering::Relaxed), String::new()));
+        let client = build_client(Duration::from_secs(5)).unwrap();
+        let target = build_target(server.url("/watch/s01e01"));
+
+        check(&client, &target).await;
+        assert!(target.health().is_healthy());
+
+        check(&client, &target).await;
+        assert!(!target.health().is_healthy());
+        assert_eq!(target.health().consecutive_failures(), 2);
+
+        status.store(200, Ordering::Relaxed);
+        check(&client, &target).await;
+        assert!(target.health().is_healthy());
+        assert_eq!(target.health().consecutive_failures(), 0);
+
+        assert_eq!(server.requests(), vec!["HEAD /watch/s01e01"; 3]);
+    }
+
+    #[tokio::test]
+    async fn check_fn_treats_missing_page_as_healthy() {
+        let server = test_server::start(|_, _| (404, String::new()));
+        let client = build_client(Duration::from_secs(5)).unwrap();
+        let target = build_target(server.url("/watch/s01e01"));
+        target.health().record_failure();
+
+        check(&client, &target).await;
+
+        assert!(target.health().is_healthy());
+    }
+
+    #[tokio::test]
+    async fn check_fn_counts_unreachable_site_as_failure() {
+        // порт освобождается сразу после bind, соединение с ним будет отклонено
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = build_client(Duration::from_secs(5)).unwrap();
+        let target = build_target(format!("http://{address}/"));
+
+        for _ in 0..FAILURE_THRESHOLD {
+            check(&client, &target).await;
+        }
+
+        assert!(!target.health().is_healthy());
+    }
+
+    #[test]
+    fn spawn_fn_does_nothing_when_disabled() {
+        let config = HealthCheckConfig {
+            interval_seconds: 0,
+            timeout_seconds: 10,
+        };
+
+        assert!(
+            spawn(
+                vec![build_target(String::from("http://localhost/"))],
+                &config
+            )
+            .is_none()
+        );
+    }
+}
@@ -1,15 +1,102 @@
-use super::WatchURLProvider;
+use super::{WatchURLProvider, template::URLTemplate};
+use crate::{
+    application::{Catalogue, Episode, Show, ShowID},
+    error::Error,
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
 
-pub fn new(watch_url_template: String) -> impl WatchURLProvider {
-    Provider { watch_url_template }
+/// Возвращает ошибку, если шаблон какого-нибудь сериала из каталога некорректен.
+pub fn new(watch_url_template: URLTemplate, catalogue: &Catalogue) -> Result<Provider, Error> {
+    let mut show_templates = HashMap::new();
+    for show in catalogue.shows() {
+        if let Some(template) = show.watch_url_template() {
+            let template: URLTemplate = template.parse()?;
+            if template.uses_page_id() {
+                return Err(Error::WatchURLTemplateError(format!(
+                    "подстановка {{id}} не поддерживается в шаблоне сериала: show={}",
+                    show.id()
+                )));
+            }
+            show_templates.insert(show.id().clone(), template);
+        }
+    }
+
+    Ok(Provider {
+        watch_url_template,
+        show_templates,
+    })
 }
+
+/// Собирает ссылку по шаблону сериала из каталога, а если его там нет, по шаблону из конфига.
 pub struct Provider {
-    watch_url_template: String,
+    watch_url_template: URLTemplate,
+    show_templates: HashMap<ShowID, URLTemplate>,
 }
 
+impl Provider {
+    pub fn template_for(&self, show: &Show) -> &URLTemplate {
+        self.show_templates
+            .get(show.id())
+            .unwrap_or(&self.watch_url_template)
+    }
+}
+
+#[async_trait]
 impl WatchURLProvider for Provider {
-    fn build_url(&self, episode: &crate::application::Episode) -> String {
-        self.watch_url_template
-            .replace("{season}", &episode.season().to_string())
+    async fn build_url(&self, show: &Show, episode: &Episode) -> Result<String, Error> {
+        Ok(self.template_for(show).render(show, episode))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build_catalogue(show_template: &str) -> Catalogue {
+        Catalogue::from_json(&format!(
+            r#"{{"shows": [
+                {{"id": "friends", "name": "Друзья", "episodes": [{{"code": "s01e02"}}]}},
+                {{"id": "the-office", "name": "Офис", "watch_url_template": "{show_template}", "episodes": [{{"code": "s02e03"}}]}}
+            ]}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_prefers_show_template() {
+        let catalogue = build_catalogue("https://office.example.com/{code}");
+        let provider = new(
+            "https://example.com/{show}/{season}".parse().unwrap(),
+            &catalogue,
+        )
+        .unwrap();
+
+        let [friends, office] = catalogue.shows() else {
+            panic!("catalogue should have two shows");
+        };
+        assert_eq!(
+            provider
+                .build_url(friends, &friends.episodes()[0])
+                .await
+                .unwrap(),
+            "https://example.com/friends/1"
+        );
+        assert_eq!(
+            provider
+                .build_url(office, &office.episodes()[0])
+                .await
+                .unwrap(),
+            "https://office.example.com/s02e03"
+        );
+    }
+
+    #[test]
+    fn new_fn_rejects_invalid_show_template() {
+        let catalogue = build_catalogue("https://office.example.com/{serie}");
+
+        let result = new("".parse().unwrap(), &catalogue);
+
+        assert!(matches!(result, Err(Error::WatchURLTemplateError(_))));
     }
 }
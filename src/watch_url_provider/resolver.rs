@@ -0,0 +1,300 @@
+//! Сервис, которому для ссылки нужен идентификатор страницы серии на сайте, а не только
+//! номера сезона и серии. Идентификатор подставляется в шаблон вместо `{id}`.
+
+use super::{WatchURLProvider, cache::Cache, template::URLTemplate};
+use crate::{
+    application::{Episode, Show, ShowID},
+    config::{ResolverConfig, ResolverSource},
+    error::Error,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Файл с идентификаторами читается сразу, поэтому ошибка в нём не даёт запустить бота.
+pub fn new(template: URLTemplate, config: ResolverConfig) -> Result<Provider, Error> {
+    let source = match config.source {
+        ResolverSource::File(path) => Source::Mapping(load_mapping(&path)?),
+        ResolverSource::Http(endpoint) => {
+            let client = reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .map_err(|err| Error::WatchURLResolveError(err.to_string()))?;
+            Source::Http { client, endpoint }
+        }
+    };
+
+    Ok(Provider {
+        template,
+        source,
+        cache: Mutex::new(Cache::new(
+            config.cache_capacity,
+            Duration::from_secs(config.cache_ttl_seconds),
+        )),
+    })
+}
+
+pub struct Provider {
+    template: URLTemplate,
+    source: Source,
+    // только для идентификаторов, полученных по HTTP, файл и так целиком в памяти
+    cache: Mutex<Cache<(ShowID, String), String>>,
+}
+
+enum Source {
+    Mapping(HashMap<ShowID, HashMap<String, String>>),
+    Http {
+        client: reqwest::Client,
+        endpoint: URLTemplate,
+    },
+}
+
+/// Идентификатор страницы, сайты используют и числа, и строки.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PageID {
+    Text(String),
+    Number(u64),
+}
+
+impl From<PageID> for String {
+    fn from(page_id: PageID) -> Self {
+        match page_id {
+            PageID::Text(text) => text,
+            PageID::Number(number) => number.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PageIDResponse {
+    id: PageID,
+}
+
+impl Provider {
+    async fn page_id(&self, show: &Show, episode: &Episode) -> Result<String, Error> {
+        let (client, endpoint) = match &self.source {
+            Source::Mapping(mapping) => {
+                return mapping
+                    .get(show.id())
+                    .and_then(|ids| ids.get(episode.code()))
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::WatchURLResolveError(format!(
+                            "в файле нет идентификатора серии: show={}, episode={}",
+                            show.id(),
+                            episode.code()
+                        ))
+                    });
+            }
+            Source::Http { client, endpoint } => (client, endpoint),
+        };
+
+        let key = (show.id().clone(), episode.code().to_string());
+        let cached = self
+            .cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key, Instant::now());
+        if let Some(page_id) = cached {
+            return Ok(page_id);
+        }
+
+        let page_id = request_page_id(client, &endpoint.render(show, episode)).await?;
+        self.cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, page_id.clone(), Instant::now());
+
+        Ok(page_id)
+    }
+}
+
+#[async_trait]
+impl WatchURLProvider for Provider {
+    async fn build_url(&self, show: &Show, episode: &Episode) -> Result<String, Error> {
+        let page_id = self.page_id(show, episode).await?;
+
+        Ok(self
+            .template
+            .render_with_page_id(show, episode, Some(&page_id)))
+    }
+}
+
+fn load_mapping(path: &Path) -> Result<HashMap<ShowID, HashMap<String, String>>, Error> {
+    let content = std::fs::read_to_string(path)?;
+    let mapping: HashMap<ShowID, HashMap<String, PageID>> = serde_json::from_str(&content)
+        .map_err(|err| {
+            Error::WatchProviderError(format!(
+                "не удалось разобрать файл с идентификаторами: path={}, error={err}",
+                path.display()
+            ))
+        })?;
+
+    Ok(mapping
+        .into_iter()
+        .map(|(show_id, ids)| {
+            let ids = ids
+                .into_iter()
+                .map(|(code, page_id)| (code.to_lowercase(), page_id.into()))
+                .collect();
+            (show_id, ids)
+        })
+        .collect())
+}
+
+async fn request_page_id(client: &reqwest::Client, url: &str) -> Result<String, Error> {
+    let resolve_error =
+        |reason: String| Error::WatchURLResolveError(format!("{reason}: url={url}"));
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| resolve_error(err.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(resolve_error(format!("сервер ответил {status}")));
+    }
+
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| resolve_error(err.to_string()))?;
+    let response: PageIDResponse =
+        serde_json::from_slice(&body).map_err(|err| resolve_error(err.to_string()))?;
+
+    Ok(response.id.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{application::Catalogue, watch_url_provider::test_server};
+    use tempfile::TempDir;
+
+    fn build_catalogue() -> Catalogue {
+        Catalogue::from_json(
+            r#"{"shows": [{"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01"}, {"code": "s01e02"}]}]}"#,
+        )
+        .unwrap()
+    }
+
+    fn build_http_provider(endpoint: String, cache_ttl_seconds: u64) -> Provider {
+        new(
+            "https://example.com/watch/{id}".parse().unwrap(),
+            ResolverConfig {
+                source: ResolverSource::Http(endpoint.parse().unwrap()),
+                cache_capacity: 16,
+                cache_ttl_seconds,
+            },
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_uses_ids_from_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ids.json");
+        std::fs::write(
+            &path,
+            r#"{"friends": {"S01E01": 4217, "s01e02": "pilot 2/b"}}"#,
+        )
+        .unwrap();
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+        let provider = new(
+            "https://example.com/watch/{id}".parse().unwrap(),
+            ResolverConfig {
+                source: ResolverSource::File(path),
+                cache_capacity: 16,
+                cache_ttl_seconds: 60,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            provider.build_url(show, &show.episodes()[0]).await.unwrap(),
+            "https://example.com/watch/4217"
+        );
+        assert_eq!(
+            provider.build_url(show, &show.episodes()[1]).await.unwrap(),
+            "https://example.com/watch/pilot%202%2Fb"
+        );
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_fails_without_id_in_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ids.json");
+        std::fs::write(&path, r#"{"friends": {"s01e01": 4217}}"#).unwrap();
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+        let provider = new(
+            "https://example.com/watch/{id}".parse().unwrap(),
+            ResolverConfig {
+                source: ResolverSource::File(path),
+                cache_capacity: 16,
+                cache_ttl_seconds: 60,
+            },
+        )
+        .unwrap();
+
+        let result = provider.build_url(show, &show.episodes()[1]).await;
+
+        assert!(matches!(result, Err(Error::WatchURLResolveError(_))));
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_requests_id_once_while_cached() {
+        let server = test_server::start(|_, path| match path {
+            "/ids/friends/s01e01" => (200, String::from(r#"{"id": 4217}"#)),
+            _ => (404, String::new()),
+        });
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+        let provider = build_http_provider(server.url("/ids/{show}/{code}"), 60);
+
+        for _ in 0..2 {
+            let result = provider.build_url(show, &show.episodes()[0]).await;
+
+            assert!(result.is_ok(), "result is error: {result:#?}");
+            assert_eq!(result.unwrap(), "https://example.com/watch/4217");
+        }
+        assert_eq!(server.requests(), vec!["GET /ids/friends/s01e01"]);
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_requests_id_again_after_ttl() {
+        let server = test_server::start(|_, _| (200, String::from(r#"{"id": "pilot"}"#)));
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+        let provider = build_http_provider(server.url("/ids/{code}"), 0);
+
+        for _ in 0..2 {
+            let result = provider.build_url(show, &show.episodes()[0]).await;
+
+            assert!(result.is_ok(), "result is error: {result:#?}");
+        }
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn provider_build_url_fn_fails_on_error_response() {
+        let server = test_server::start(|_, _| (404, String::from("not found")));
+        let catalogue = build_catalogue();
+        let show = catalogue.default_show();
+        let provider = build_http_provider(server.url("/ids/{code}"), 60);
+
+        let result = provider.build_url(show, &show.episodes()[0]).await;
+
+        assert!(matches!(result, Err(Error::WatchURLResolveError(_))));
+    }
+}
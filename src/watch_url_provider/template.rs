@@ -0,0 +1,333 @@
+//! Шаблоны ссылок для просмотра серий.
+//!
+//! Шаблон это строка с подстановками в фигурных скобках:
+//!
+//! - `{season}` и `{episode}` — номера сезона и серии, `{season:02}` и `{episode:02}`
+//!   дополняют номер нулями слева до указанной ширины, не больше [`MAX_WIDTH`];
+//! - `{code}` — код серии, например `s01e05`;
+//! - `{title_slug}` — оригинальное название серии в виде `the-one-with-the-thumb`,
+//!   а если названия в каталоге нет, код серии;
+//! - `{show}` — идентификатор сериала из каталога;
+//! - `{id}` — идентификатор страницы серии на сайте. Его находит сервис с `resolver`
+//!   в конфиге, в остальных шаблонах эта подстановка запрещена.
+//!
+//! Чтобы вставить фигурную скобку как есть, её нужно удвоить: `{{` и `}}`.
+
+use crate::{
+    application::{Episode, Show},
+    error::Error,
+};
+use std::str::FromStr;
+
+/// Номера сезонов и серий двузначные, шире дополнять незачем.
+pub const MAX_WIDTH: usize = 4;
+
+/// Шаблон, разобранный и проверенный заранее, чтобы ошибка в нём находилась при запуске,
+/// а не при первой отправке ссылки.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct URLTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Season { width: usize },
+    Episode { width: usize },
+    Code,
+    TitleSlug,
+    Show,
+    PageID,
+}
+
+impl URLTemplate {
+    pub fn render(&self, show: &Show, episode: &Episode) -> String {
+        self.render_with_page_id(show, episode, None)
+    }
+
+    /// Подставляет `page_id` вместо `{id}`. Без идентификатора `{id}` заменяется кодом серии.
+    ///
+    /// Идентификатор приходит с чужого сайта, поэтому кодируется: в пути ссылки как сегмент
+    /// пути, после `?` или `#` как значение параметра. Так `/`, `?`, `#` и пробелы в нём
+    /// не ломают ссылку.
+    pub fn render_with_page_id(
+        &self,
+        show: &Show,
+        episode: &Episode,
+        page_id: Option<&str>,
+    ) -> String {
+        let mut url = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => url.push_str(text),
+                Part::Season { width } => {
+                    url.push_str(&format!("{:0width$}", episode.season()));
+                }
+                Part::Episode { width } => {
+                    url.push_str(&format!("{:0width$}", episode.episode()));
+                }
+                Part::Code => url.push_str(episode.code()),
+                Part::TitleSlug => url.push_str(&title_slug(episode)),
+                Part::Show => url.push_str(show.id().as_str()),
+                Part::PageID => match page_id {
+                    Some(page_id) if url.contains(['?', '#']) => {
+                        url.extend(url::form_urlencoded::byte_serialize(page_id.as_bytes()))
+                    }
+                    Some(page_id) => url.push_str(&encode_path_segment(page_id)),
+                    None => url.push_str(episode.code()),
+                },
+            }
+        }
+
+        url
+    }
+
+    pub fn uses_page_id(&self) -> bool {
+        self.parts.contains(&Part::PageID)
+    }
+}
+
+impl FromStr for URLTemplate {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let template_error =
+            |reason: &str| Error::WatchURLTemplateError(format!("{reason}: template={template}"));
+
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(template_error("лишняя закрывающая скобка")),
+                '{' => {
+                    let (placeholder, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| template_error("подстановка не закрыта скобкой"))?;
+                    let part = parse_placeholder(placeholder).ok_or_else(|| {
+                        template_error(&format!("неизвестная подстановка {{{placeholder}}}"))
+                    })?;
+                    chars = rest.chars();
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(part);
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl TryFrom<String> for URLTemplate {
+    type Error = Error;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        template.parse()
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Option<Part> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, Some(format)),
+        None => (placeholder, None),
+    };
+
+    // ширина задаётся только для номеров и только с нулём впереди, как в `format!`
+    let width = match format {
+        None => 0,
+        Some(format) => format
+            .strip_prefix('0')
+            .filter(|width| !width.is_empty() && width.bytes().all(|b| b.is_ascii_digit()))?
+            .parse()
+            .ok()
+            .filter(|&width| width <= MAX_WIDTH)?,
+    };
+
+    match (name, format) {
+        ("season", _) => Some(Part::Season { width }),
+        ("episode", _) => Some(Part::Episode { width }),
+        ("code", None) => Some(Part::Code),
+        ("title_slug", None) => Some(Part::TitleSlug),
+        ("show", None) => Some(Part::Show),
+        ("id", None) => Some(Part::PageID),
+        _ => None,
+    }
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    let mut url = url::Url::parse("http://localhost/").expect("ссылка-заготовка корректна");
+    url.path_segments_mut()
+        .expect("у http-ссылки есть путь")
+        .pop_if_empty()
+        .push(segment);
+
+    url.path()[1..].to_string()
+}
+
+fn title_slug(episode: &Episode) -> String {
+    let Some(title) = episode
+        .metadata()
+        .and_then(|metadata| metadata.title.as_deref())
+    else {
+        return episode.code().to_string();
+    };
+
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if c != '\'' && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+
+    match slug.is_empty() {
+        true => episode.code().to_string(),
+        false => slug.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application::{Catalogue, EpisodeMetadata, ShowID};
+
+    fn build_catalogue() -> Catalogue {
+        Catalogue::from_json(
+            r#"{"shows": [
+                {"id": "friends", "name": "Друзья", "episodes": [{"code": "s01e01"}]},
+                {"id": "the-office", "name": "Офис", "episodes": [{"code": "s02e05"}]}
+            ]}"#,
+        )
+        .unwrap()
+    }
+
+    fn office(catalogue: &Catalogue) -> &Show {
+        catalogue.show(&ShowID::new("the-office")).unwrap()
+    }
+
+    fn render(template: &str, episode: &Episode) -> String {
+        let catalogue = build_catalogue();
+        let template: URLTemplate = template.parse().unwrap();
+
+        template.render(office(&catalogue), episode)
+    }
+
+    fn episode() -> Episode {
+        Episode::try_from("s02e05").unwrap()
+    }
+
+    #[test]
+    fn url_template_render_fn_substitutes_numbers() {
+        assert_eq!(render("/{season}/{episode}", &episode()), "/2/5");
+        assert_eq!(render("/{season:02}x{episode:03}", &episode()), "/02x005");
+        assert_eq!(render("/{season:04}", &episode()), "/0002");
+    }
+
+    #[test]
+    fn url_template_render_fn_substitutes_code_and_show() {
+        assert_eq!(
+            render("https://example.com/{show}/{code}", &episode()),
+            "https://example.com/the-office/s02e05"
+        );
+    }
+
+    #[test]
+    fn url_template_render_fn_substitutes_title_slug() {
+        let episode = episode().with_metadata(EpisodeMetadata {
+            title: Some(String::from("The Dundies: Michael's Night!")),
+            localized_title: Some(String::from("Премия Данди")),
+            ..EpisodeMetadata::default()
+        });
+
+        assert_eq!(
+            render("/{title_slug}", &episode),
+            "/the-dundies-michaels-night"
+        );
+    }
+
+    #[test]
+    fn url_template_render_fn_uses_code_as_slug_without_title() {
+        assert_eq!(render("/{title_slug}", &episode()), "/s02e05");
+    }
+
+    #[test]
+    fn url_template_render_with_page_id_fn_substitutes_page_id() {
+        let catalogue = build_catalogue();
+        let template: URLTemplate = "https://example.com/watch/{id}".parse().unwrap();
+
+        assert!(template.uses_page_id());
+        assert_eq!(
+            template.render_with_page_id(office(&catalogue), &episode(), Some("4217")),
+            "https://example.com/watch/4217"
+        );
+        assert_eq!(
+            template.render(office(&catalogue), &episode()),
+            "https://example.com/watch/s02e05"
+        );
+    }
+
+    #[test]
+    fn url_template_render_with_page_id_fn_encodes_page_id() {
+        let catalogue = build_catalogue();
+        let template: URLTemplate = "https://example.com/watch/{id}?id={id}#{id}"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            template.render_with_page_id(office(&catalogue), &episode(), Some("a b/c?d#e%")),
+            "https://example.com/watch/a%20b%2Fc%3Fd%23e%25?id=a+b%2Fc%3Fd%23e%25#a+b%2Fc%3Fd%23e%25"
+        );
+    }
+
+    #[test]
+    fn url_template_render_fn_keeps_text_and_escaped_braces() {
+        assert_eq!(render("", &episode()), "");
+        assert_eq!(
+            render("https://example.com/?q={{{code}}}", &episode()),
+            "https://example.com/?q={s02e05}"
+        );
+    }
+
+    #[test]
+    fn url_template_from_str_fn_rejects_invalid_templates() {
+        for template in [
+            "{unknown}",
+            "{Season}",
+            "{season",
+            "season}",
+            "{code:02}",
+            "{season:2}",
+            "{season:0}",
+            "{season:0x}",
+            "{season:05}",
+            "{episode:0999999}",
+            "{episode:099999999999999999999999}",
+            "{}",
+        ] {
+            let result = template.parse::<URLTemplate>();
+
+            assert!(
+                matches!(result, Err(Error::WatchURLTemplateError(_))),
+                "template: {template}, result: {result:#?}"
+            );
+        }
+    }
+}
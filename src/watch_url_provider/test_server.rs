@@ -0,0 +1,82 @@
+//! HTTP-сервер для тестов сервисов, которые ходят в сеть.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Запускает сервер на свободном порту. `respond` получает метод и путь запроса
+/// и возвращает код ответа и тело. Сервер работает, пока не завершится процесс тестов.
+pub fn start<F>(respond: F) -> TestServer
+where
+    F: Fn(&str, &str) -> (u16, String) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let server_requests = Arc::clone(&requests);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            handle(stream, &respond, &server_requests);
+        }
+    });
+
+    TestServer { address, requests }
+}
+
+pub struct TestServer {
+    address: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    /// Полученные запросы в виде `GET /path`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle<F>(stream: TcpStream, respond: &F, requests: &Mutex<Vec<String>>)
+where
+    F: Fn(&str, &str) -> (u16, String),
+{
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // заголовки не нужны, но их надо дочитать до пустой строки
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line.trim().is_empty() => break,
+            Ok(_) => {}
+        }
+    }
+
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    requests.lock().unwrap().push(format!("{method} {path}"));
+
+    let (status, body) = respond(method, path);
+    let mut response = format!(
+        "HTTP/1.1 {status} Test\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    let _ = (&stream).write_all(response.as_bytes());
+}